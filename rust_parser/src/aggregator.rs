@@ -1,7 +1,74 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
 use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 
+use crate::anomaly::{detect_anomalies, Anomaly, AnomalyConfig};
+use crate::cardinality::UniqueCounter;
+use crate::crawl_import::CrawlReport;
+use crate::heavy_hitters::{keep_heaviest, HeavyHittersSummary, SpaceSaving};
 use crate::parser::LogEntry;
+use crate::path_tree::{build_path_tree, PathNode, SectionRow};
+use crate::quantile::{DdSketch, LatencySummary};
+use crate::rate_detector::{Burst, RateConfig, RateWindows};
+use crate::sitemap::SitemapCoverage;
+use crate::status_consistency::{
+    find_bot_inconsistencies, find_inconsistencies, StatusInconsistency, StatusTimeline,
+};
+use crate::time_bucket::TimeBucket;
+use crate::time_series::{Heatmap, TimeSeries, TimeSeriesColumns};
+
+/// Directory levels rolled up in the path tree by default
+const DEFAULT_PATH_DEPTH: usize = 3;
+
+/// Raw spellings tracked per normalized URL
+const MAX_URL_VARIANTS: usize = 32;
+
+/// Entries between prunes of the per-URL maps in heavy hitters mode
+const PRUNE_INTERVAL: u64 = 4096;
+
+/// Aggregation options carried by a `Statistics` instance
+#[derive(Debug, Clone)]
+pub struct StatsConfig {
+    /// Collect per-client rate windows for burst detection. Off by default, as the
+    /// windows grow with the number of client IPs times minutes in the logs.
+    pub rate: Option<RateConfig>,
+    /// Count unique URLs/IPs with HyperLogLog at this standard error instead of exact
+    /// sets. The per-URL and per-IP maps are not affected.
+    pub approx_unique_error: Option<f64>,
+    /// Track top URLs (overall and per bot) with Space-Saving summaries of this
+    /// capacity instead of keeping stats for every distinct URL. The other per-URL
+    /// and per-IP maps are pruned to their heaviest keys, so their counts are approximate.
+    pub heavy_hitters: Option<usize>,
+    /// Directory levels below the root in the path tree rollup
+    pub path_depth: usize,
+    /// Bucket size of `hits_by_time_bucket` and the time series
+    pub time_bucket: TimeBucket,
+    /// Add a per-domain dimension to the time series
+    pub time_series_by_domain: bool,
+    pub anomaly: AnomalyConfig,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        StatsConfig {
+            rate: None,
+            approx_unique_error: None,
+            heavy_hitters: None,
+            path_depth: DEFAULT_PATH_DEPTH,
+            time_bucket: TimeBucket::default(),
+            time_series_by_domain: false,
+            anomaly: AnomalyConfig::default(),
+        }
+    }
+}
+
+impl StatsConfig {
+    /// Distinct value counter in the configured (exact or approximate) mode
+    pub fn unique_counter(&self) -> UniqueCounter {
+        UniqueCounter::new(self.approx_unique_error)
+    }
+}
 
 /// Statistics for a specific URL
 #[derive(Debug, Clone, Default, Serialize)]
@@ -11,6 +78,11 @@ pub struct UrlStats {
     pub human_hits: u64,
     pub bytes_total: u64,
     pub status_codes: HashMap<u16, u64>,
+    /// When each status code was returned
+    pub status_runs: StatusTimeline,
+    pub googlebot_types: HashMap<String, u64>,
+    /// Domain -> traffic on that domain, as URLs are keyed by path only
+    pub by_domain: HashMap<String, TrafficSplit>,
 }
 
 impl UrlStats {
@@ -22,17 +94,102 @@ impl UrlStats {
         for (code, count) in &other.status_codes {
             *self.status_codes.entry(*code).or_insert(0) += count;
         }
+        self.status_runs.merge(&other.status_runs);
+        for (crawler_type, count) in &other.googlebot_types {
+            *self.googlebot_types.entry(crawler_type.clone()).or_insert(0) += count;
+        }
+        for (domain, traffic) in &other.by_domain {
+            self.by_domain.entry(domain.clone()).or_default().merge(traffic);
+        }
     }
 }
 
+/// Hit counts by HTTP status class
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct StatusClassCounts {
+    pub status_2xx: u64,
+    pub status_3xx: u64,
+    pub status_4xx: u64,
+    pub status_5xx: u64,
+    pub status_other: u64,
+}
+
+impl StatusClassCounts {
+    pub fn add(&mut self, status_code: u16) {
+        self.add_count(status_code, 1);
+    }
+
+    pub fn add_count(&mut self, status_code: u16, count: u64) {
+        match status_code {
+            200..=299 => self.status_2xx += count,
+            300..=399 => self.status_3xx += count,
+            400..=499 => self.status_4xx += count,
+            500..=599 => self.status_5xx += count,
+            _ => self.status_other += count,
+        }
+    }
+
+    pub fn merge(&mut self, other: &StatusClassCounts) {
+        self.status_2xx += other.status_2xx;
+        self.status_3xx += other.status_3xx;
+        self.status_4xx += other.status_4xx;
+        self.status_5xx += other.status_5xx;
+        self.status_other += other.status_other;
+    }
+
+    /// Client and server errors (4xx + 5xx)
+    pub fn errors(&self) -> u64 {
+        self.status_4xx + self.status_5xx
+    }
+}
+
+/// Status class label ("2xx", "4xx", ...) for a status code
+pub fn status_class_label(status_code: u16) -> &'static str {
+    match status_code {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        500..=599 => "5xx",
+        _ => "other",
+    }
+}
+
+/// Whether a status code is a client or server error
+pub fn is_error_status(status_code: u16) -> bool {
+    (400..600).contains(&status_code)
+}
+
+/// `part` as a percentage of `whole`, 0 when `whole` is 0
+pub fn share(part: u64, whole: u64) -> f64 {
+    if whole > 0 {
+        part as f64 / whole as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// The `top_n` largest counts, ties broken by key
+pub fn top_counts(counts: &HashMap<String, u64>, top_n: usize) -> Vec<(String, u64)> {
+    let mut counts: Vec<(String, u64)> = counts.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(top_n);
+    counts
+}
+
 /// Statistics for a specific bot
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct BotStats {
     pub hits: u64,
     pub category: String,
-    pub unique_urls: HashSet<String>,
+    pub unique_urls: UniqueCounter,
     pub verified_count: u64,
     pub unverified_count: u64,
+    pub bytes_total: u64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+    /// URLs that returned 4xx/5xx to this bot, with error hit counts
+    pub error_urls: HashMap<String, u64>,
 }
 
 impl BotStats {
@@ -41,9 +198,458 @@ impl BotStats {
         if self.category.is_empty() {
             self.category = other.category.clone();
         }
-        self.unique_urls.extend(other.unique_urls.iter().cloned());
+        self.unique_urls.merge(&other.unique_urls);
         self.verified_count += other.verified_count;
         self.unverified_count += other.unverified_count;
+        self.bytes_total += other.bytes_total;
+        self.status_classes.merge(&other.status_classes);
+        for (url, count) in &other.error_urls {
+            *self.error_urls.entry(url.clone()).or_insert(0) += count;
+        }
+    }
+
+    /// Average response size in bytes
+    pub fn avg_bytes(&self) -> f64 {
+        if self.hits > 0 {
+            self.bytes_total as f64 / self.hits as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Share of hits that returned 4xx/5xx, as a percentage
+    pub fn error_rate(&self) -> f64 {
+        if self.hits > 0 {
+            (self.status_classes.errors() as f64 / self.hits as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// First and last time something was seen
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TimeRange {
+    pub first_seen: Option<DateTime<FixedOffset>>,
+    pub last_seen: Option<DateTime<FixedOffset>>,
+}
+
+impl TimeRange {
+    pub fn add(&mut self, dt: DateTime<FixedOffset>) {
+        if self.first_seen.is_none_or(|first| dt < first) {
+            self.first_seen = Some(dt);
+        }
+        if self.last_seen.is_none_or(|last| dt > last) {
+            self.last_seen = Some(dt);
+        }
+    }
+
+    pub fn merge(&mut self, other: &TimeRange) {
+        if let Some(first) = other.first_seen {
+            self.add(first);
+        }
+        if let Some(last) = other.last_seen {
+            self.add(last);
+        }
+    }
+}
+
+/// Hits and crawl times of a bot/URL pair on one domain
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DomainCrawl {
+    pub hits: u64,
+    #[serde(flatten)]
+    pub seen: TimeRange,
+}
+
+impl DomainCrawl {
+    pub fn merge(&mut self, other: &DomainCrawl) {
+        self.hits += other.hits;
+        self.seen.merge(&other.seen);
+    }
+}
+
+/// Crawl statistics for a single bot/URL pair
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BotUrlStats {
+    pub hits: u64,
+    pub bytes_total: u64,
+    pub status_codes: HashMap<u16, u64>,
+    #[serde(flatten)]
+    pub seen: TimeRange,
+    /// When each status code was returned
+    pub status_runs: StatusTimeline,
+    /// Domain -> hits on that domain, as URLs are keyed by path only
+    pub by_domain: HashMap<String, DomainCrawl>,
+}
+
+impl BotUrlStats {
+    pub fn merge(&mut self, other: &BotUrlStats) {
+        self.hits += other.hits;
+        self.bytes_total += other.bytes_total;
+        for (code, count) in &other.status_codes {
+            *self.status_codes.entry(*code).or_insert(0) += count;
+        }
+        self.seen.merge(&other.seen);
+        self.status_runs.merge(&other.status_runs);
+        for (domain, crawl) in &other.by_domain {
+            self.by_domain.entry(domain.clone()).or_default().merge(crawl);
+        }
+    }
+}
+
+/// Hit counts split between bots and humans
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrafficSplit {
+    pub hits: u64,
+    pub bot_hits: u64,
+    pub human_hits: u64,
+}
+
+impl TrafficSplit {
+    fn add(&mut self, is_bot: bool) {
+        self.hits += 1;
+        if is_bot {
+            self.bot_hits += 1;
+        } else {
+            self.human_hits += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &TrafficSplit) {
+        self.hits += other.hits;
+        self.bot_hits += other.bot_hits;
+        self.human_hits += other.human_hits;
+    }
+}
+
+/// Statistics for an autonomous system (network operator)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AsnStats {
+    pub organization: String,
+    #[serde(flatten)]
+    pub traffic: TrafficSplit,
+}
+
+impl AsnStats {
+    pub fn merge(&mut self, other: &AsnStats) {
+        if self.organization.is_empty() {
+            self.organization = other.organization.clone();
+        }
+        self.traffic.merge(&other.traffic);
+    }
+}
+
+/// Statistics for a single attacking client IP
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AttackerStats {
+    pub hits: u64,
+    pub categories: HashMap<String, u64>,
+}
+
+impl AttackerStats {
+    pub fn merge(&mut self, other: &AttackerStats) {
+        self.hits += other.hits;
+        for (category, count) in &other.categories {
+            *self.categories.entry(category.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+/// Security scanner and attack probe statistics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AttackStats {
+    pub total: u64,
+    pub not_found: u64,
+    pub by_category: HashMap<String, u64>,
+    pub by_ip: HashMap<String, AttackerStats>,
+    pub by_url: HashMap<String, u64>,
+}
+
+impl AttackStats {
+    fn add_entry(&mut self, entry: &LogEntry) {
+        self.total += 1;
+        if entry.status_code == 404 {
+            self.not_found += 1;
+        }
+        *self.by_category.entry(entry.threat_category.clone()).or_insert(0) += 1;
+        *self.by_url.entry(entry.url.clone()).or_insert(0) += 1;
+        if let Some(ip) = entry.ips.first() {
+            let attacker = self.by_ip.entry(ip.clone()).or_default();
+            attacker.hits += 1;
+            *attacker.categories.entry(entry.threat_category.clone()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &AttackStats) {
+        self.total += other.total;
+        self.not_found += other.not_found;
+        for (category, count) in &other.by_category {
+            *self.by_category.entry(category.clone()).or_insert(0) += count;
+        }
+        for (ip, stats) in &other.by_ip {
+            self.by_ip.entry(ip.clone()).or_default().merge(stats);
+        }
+        for (url, count) in &other.by_url {
+            *self.by_url.entry(url.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+/// Hits, bytes and status classes for one resource type and bot
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceStats {
+    pub hits: u64,
+    pub bytes_total: u64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+}
+
+impl ResourceStats {
+    fn add_entry(&mut self, entry: &LogEntry) {
+        self.hits += 1;
+        self.bytes_total += entry.bytes_sent;
+        self.status_classes.add(entry.status_code);
+    }
+
+    pub fn merge(&mut self, other: &ResourceStats) {
+        self.hits += other.hits;
+        self.bytes_total += other.bytes_total;
+        self.status_classes.merge(&other.status_classes);
+    }
+}
+
+/// Traffic for one resource type (html, js, image, ...), overall and per bot
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceTypeStats {
+    #[serde(flatten)]
+    pub totals: ResourceStats,
+    pub bot_hits: u64,
+    pub human_hits: u64,
+    pub by_bot: HashMap<String, ResourceStats>,
+}
+
+impl ResourceTypeStats {
+    fn add_entry(&mut self, entry: &LogEntry) {
+        self.totals.add_entry(entry);
+        if entry.is_bot {
+            self.bot_hits += 1;
+            if !entry.bot_name.is_empty() {
+                self.by_bot.entry(entry.bot_name.clone()).or_default().add_entry(entry);
+            }
+        } else {
+            self.human_hits += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &ResourceTypeStats) {
+        self.totals.merge(&other.totals);
+        self.bot_hits += other.bot_hits;
+        self.human_hits += other.human_hits;
+        for (bot, stats) in &other.by_bot {
+            self.by_bot.entry(bot.clone()).or_default().merge(stats);
+        }
+    }
+}
+
+/// Traffic for one page type from the segment rules
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SegmentStats {
+    pub hits: u64,
+    pub bot_hits: u64,
+    pub human_hits: u64,
+    pub bytes_total: u64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+    pub unique_urls: UniqueCounter,
+    pub hits_by_bot: HashMap<String, u64>,
+    pub bot_hits_by_date: HashMap<String, u64>,
+}
+
+impl SegmentStats {
+    fn add_entry(&mut self, entry: &LogEntry) {
+        self.hits += 1;
+        self.bytes_total += entry.bytes_sent;
+        self.status_classes.add(entry.status_code);
+        self.unique_urls.insert(&entry.url);
+        if entry.is_bot {
+            self.bot_hits += 1;
+            if !entry.bot_name.is_empty() {
+                *self.hits_by_bot.entry(entry.bot_name.clone()).or_insert(0) += 1;
+            }
+            if let Some(dt) = entry.datetime {
+                *self
+                    .bot_hits_by_date
+                    .entry(dt.format("%Y-%m-%d").to_string())
+                    .or_insert(0) += 1;
+            }
+        } else {
+            self.human_hits += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &SegmentStats) {
+        self.hits += other.hits;
+        self.bot_hits += other.bot_hits;
+        self.human_hits += other.human_hits;
+        self.bytes_total += other.bytes_total;
+        self.status_classes.merge(&other.status_classes);
+        self.unique_urls.merge(&other.unique_urls);
+        for (bot, count) in &other.hits_by_bot {
+            *self.hits_by_bot.entry(bot.clone()).or_insert(0) += count;
+        }
+        for (date, count) in &other.bot_hits_by_date {
+            *self.bot_hits_by_date.entry(date.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+/// robots.txt compliance of one bot
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RobotsBotStats {
+    pub checked_hits: u64,
+    pub disallowed_hits: u64,
+    /// Disallowed URLs the bot requested anyway
+    pub disallowed_urls: HashMap<String, u64>,
+}
+
+impl RobotsBotStats {
+    pub fn merge(&mut self, other: &RobotsBotStats) {
+        self.checked_hits += other.checked_hits;
+        self.disallowed_hits += other.disallowed_hits;
+        for (url, count) in &other.disallowed_urls {
+            *self.disallowed_urls.entry(url.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+/// Requests checked against robots.txt
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RobotsStats {
+    pub by_bot: HashMap<String, RobotsBotStats>,
+    pub blocked_human_hits: u64,
+    /// URLs blocked for `HUMAN_TRAFFIC_AGENT` that humans visited
+    pub blocked_human_urls: HashMap<String, u64>,
+}
+
+impl RobotsStats {
+    fn add_entry(&mut self, entry: &LogEntry, allowed: bool) {
+        if entry.is_bot {
+            if entry.bot_name.is_empty() {
+                return;
+            }
+            let bot_stats = self.by_bot.entry(entry.bot_name.clone()).or_default();
+            bot_stats.checked_hits += 1;
+            if !allowed {
+                bot_stats.disallowed_hits += 1;
+                *bot_stats.disallowed_urls.entry(entry.url.clone()).or_insert(0) += 1;
+            }
+        } else if !allowed {
+            self.blocked_human_hits += 1;
+            *self.blocked_human_urls.entry(entry.url.clone()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &RobotsStats) {
+        for (bot, stats) in &other.by_bot {
+            self.by_bot.entry(bot.clone()).or_default().merge(stats);
+        }
+        self.blocked_human_hits += other.blocked_human_hits;
+        for (url, count) in &other.blocked_human_urls {
+            *self.blocked_human_urls.entry(url.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+/// Bot traffic for a single query parameter name
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryParamStats {
+    pub bot_hits: u64,
+    pub googlebot_hits: u64,
+    pub values: UniqueCounter,
+    /// Distinct URLs (path and query) the parameter appeared in
+    pub urls: UniqueCounter,
+}
+
+impl QueryParamStats {
+    pub fn merge(&mut self, other: &QueryParamStats) {
+        self.bot_hits += other.bot_hits;
+        self.googlebot_hits += other.googlebot_hits;
+        self.values.merge(&other.values);
+        self.urls.merge(&other.urls);
+    }
+}
+
+/// Bot crawl activity on parameterized URLs
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryParamAnalytics {
+    pub bot_hits: u64,
+    pub parameterized_bot_hits: u64,
+    pub googlebot_hits: u64,
+    pub parameterized_googlebot_hits: u64,
+    pub by_param: HashMap<String, QueryParamStats>,
+    /// Sorted parameter names joined with `&` -> bot hits
+    pub combinations: HashMap<String, u64>,
+}
+
+impl QueryParamAnalytics {
+    fn add_entry(&mut self, entry: &LogEntry, config: &StatsConfig) {
+        let is_googlebot = !entry.googlebot_type.is_empty();
+        self.bot_hits += 1;
+        if is_googlebot {
+            self.googlebot_hits += 1;
+        }
+
+        let query = match entry.url.split_once('?') {
+            Some((_, query)) => query.split('#').next().unwrap_or(""),
+            None => return,
+        };
+        let mut params: Vec<(&str, &str)> = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|param| param.split_once('=').unwrap_or((param, "")))
+            .collect();
+        if params.is_empty() {
+            return;
+        }
+        // A parameter repeated in the query (`?a=1&a=2`) counts one hit, but every value
+        params.sort_by_key(|&(name, _)| name);
+        for (i, &(name, value)) in params.iter().enumerate() {
+            let stats = self.by_param.entry(name.to_string()).or_insert_with(|| QueryParamStats {
+                values: config.unique_counter(),
+                urls: config.unique_counter(),
+                ..Default::default()
+            });
+            if i == 0 || params[i - 1].0 != name {
+                stats.bot_hits += 1;
+                if is_googlebot {
+                    stats.googlebot_hits += 1;
+                }
+                stats.urls.insert(&entry.url);
+            }
+            stats.values.insert(value);
+        }
+
+        self.parameterized_bot_hits += 1;
+        if is_googlebot {
+            self.parameterized_googlebot_hits += 1;
+        }
+        let mut names: Vec<&str> = params.iter().map(|&(name, _)| name).collect();
+        names.dedup();
+        *self.combinations.entry(names.join("&")).or_insert(0) += 1;
+    }
+
+    pub fn merge(&mut self, other: &QueryParamAnalytics) {
+        self.bot_hits += other.bot_hits;
+        self.parameterized_bot_hits += other.parameterized_bot_hits;
+        self.googlebot_hits += other.googlebot_hits;
+        self.parameterized_googlebot_hits += other.parameterized_googlebot_hits;
+        for (name, stats) in &other.by_param {
+            self.by_param.entry(name.clone()).or_default().merge(stats);
+        }
+        for (combination, count) in &other.combinations {
+            *self.combinations.entry(combination.clone()).or_insert(0) += count;
+        }
     }
 }
 
@@ -52,20 +658,61 @@ impl BotStats {
 pub struct Statistics {
     pub total_requests: u64,
     pub total_bytes: u64,
-    pub unique_urls: HashSet<String>,
-    pub unique_ips: HashSet<String>,
+    pub unique_urls: UniqueCounter,
+    pub unique_ips: UniqueCounter,
     pub bot_requests: u64,
     pub human_requests: u64,
     pub verified_googlebot_requests: u64,
     pub status_distribution: HashMap<u16, u64>,
     pub hits_by_url: HashMap<String, UrlStats>,
     pub hits_by_bot: HashMap<String, BotStats>,
+    /// Bot name -> URL -> crawl stats
+    pub hits_by_bot_url: HashMap<String, HashMap<String, BotUrlStats>>,
     pub hits_by_category: HashMap<String, u64>,
+    pub hits_by_googlebot_type: HashMap<String, u64>,
     pub hits_by_hour: HashMap<String, u64>,
     pub hits_by_date: HashMap<String, u64>,
+    /// Hits per bucket of the configured size
+    pub hits_by_time_bucket: HashMap<String, u64>,
+    /// Entries whose timestamp could not be parsed (left out of time stats)
+    pub unparsed_timestamps: u64,
+    /// Hits per time bucket by traffic type, bot, bot category, status class,
+    /// segment and (optionally) domain
+    pub time_series: TimeSeries,
+    /// Hourly hits by bot and status class in UTC, the input of anomaly detection
+    pub hourly_series: TimeSeries,
+    /// UTC hour -> UTC offset (seconds) of its timestamps, to label anomalies in local time
+    pub hourly_offsets: BTreeMap<NaiveDateTime, i32>,
+    /// Bot category (or `human`) -> day of week x hour of day hits
+    pub activity_heatmaps: HashMap<String, Heatmap>,
     pub hits_by_method: HashMap<String, u64>,
     pub hits_by_domain: HashMap<String, u64>,
     pub hits_by_server: HashMap<String, u64>,
+    pub latest_timestamp: Option<DateTime<FixedOffset>>,
+    pub hits_by_country: HashMap<String, TrafficSplit>,
+    pub hits_by_asn: HashMap<u32, AsnStats>,
+    pub attacks: AttackStats,
+    /// Clients sending the Googlebot user agent from outside Googlebot's ranges
+    pub spoofed_googlebot_ips: HashMap<String, u64>,
+    pub rate_windows: RateWindows,
+    pub latency: DdSketch,
+    pub latency_by_url: HashMap<String, DdSketch>,
+    pub latency_by_bot: HashMap<String, DdSketch>,
+    pub latency_by_status_class: HashMap<String, DdSketch>,
+    pub latency_by_hour: HashMap<String, DdSketch>,
+    /// Approximate top URLs, in heavy hitters mode
+    pub url_heavy_hitters: Option<SpaceSaving>,
+    /// Bot name -> approximate top URLs, in heavy hitters mode
+    pub bot_url_heavy_hitters: HashMap<String, SpaceSaving>,
+    /// Normalized URL -> most frequent raw URLs normalization rewrote to it. The hits
+    /// logged with the normalized spelling are the rest of the URL's hits.
+    pub url_variants: HashMap<String, SpaceSaving>,
+    pub query_params: QueryParamAnalytics,
+    pub hits_by_segment: HashMap<String, SegmentStats>,
+    pub hits_by_resource_type: HashMap<String, ResourceTypeStats>,
+    pub robots: RobotsStats,
+    #[serde(skip)]
+    pub config: StatsConfig,
 }
 
 impl Statistics {
@@ -74,15 +721,26 @@ impl Statistics {
         Self::default()
     }
 
+    /// Create new empty statistics with the given aggregation options
+    pub fn with_config(config: StatsConfig) -> Self {
+        Statistics {
+            unique_urls: config.unique_counter(),
+            unique_ips: config.unique_counter(),
+            url_heavy_hitters: config.heavy_hitters.map(SpaceSaving::new),
+            config,
+            ..Self::default()
+        }
+    }
+
     /// Add a single log entry to the statistics
     pub fn add_entry(&mut self, entry: &LogEntry) {
         self.total_requests += 1;
         self.total_bytes += entry.bytes_sent;
 
         // Track unique URLs and IPs
-        self.unique_urls.insert(entry.url.clone());
+        self.unique_urls.insert(&entry.url);
         for ip in &entry.ips {
-            self.unique_ips.insert(ip.clone());
+            self.unique_ips.insert(ip);
         }
 
         // Bot vs human
@@ -98,31 +756,99 @@ impl Statistics {
         // Status codes
         *self.status_distribution.entry(entry.status_code).or_insert(0) += 1;
 
-        // URL stats
-        let url_stats = self.hits_by_url.entry(entry.url.clone()).or_default();
-        url_stats.hits += 1;
-        url_stats.bytes_total += entry.bytes_sent;
-        *url_stats.status_codes.entry(entry.status_code).or_insert(0) += 1;
-        if entry.is_bot {
-            url_stats.bot_hits += 1;
+        // URL stats; heavy hitters mode only keeps a bounded summary
+        let heavy_hitters = self.config.heavy_hitters;
+        if let Some(top_urls) = &mut self.url_heavy_hitters {
+            top_urls.insert(&entry.url);
         } else {
-            url_stats.human_hits += 1;
+            let url_stats = self.hits_by_url.entry(entry.url.clone()).or_default();
+            url_stats.hits += 1;
+            url_stats.bytes_total += entry.bytes_sent;
+            *url_stats.status_codes.entry(entry.status_code).or_insert(0) += 1;
+            if let Some(dt) = entry.datetime {
+                url_stats.status_runs.add(entry.status_code, dt);
+            }
+            if entry.is_bot {
+                url_stats.bot_hits += 1;
+            } else {
+                url_stats.human_hits += 1;
+            }
+            url_stats.by_domain.entry(entry.domain.clone()).or_default().add(entry.is_bot);
+            if !entry.googlebot_type.is_empty() {
+                *url_stats.googlebot_types.entry(entry.googlebot_type.clone()).or_insert(0) += 1;
+            }
+            if !entry.raw_url.is_empty() {
+                self.url_variants
+                    .entry(entry.url.clone())
+                    .or_insert_with(|| SpaceSaving::new(MAX_URL_VARIANTS))
+                    .insert(&entry.raw_url);
+            }
         }
 
         // Bot stats
         if entry.is_bot && !entry.bot_name.is_empty() {
-            let bot_stats = self.hits_by_bot.entry(entry.bot_name.clone()).or_default();
+            let bot_stats = self
+                .hits_by_bot
+                .entry(entry.bot_name.clone())
+                .or_insert_with(|| BotStats {
+                    unique_urls: self.config.unique_counter(),
+                    ..Default::default()
+                });
             bot_stats.hits += 1;
             bot_stats.category = entry.bot_category.clone();
-            bot_stats.unique_urls.insert(entry.url.clone());
+            bot_stats.unique_urls.insert(&entry.url);
+            bot_stats.bytes_total += entry.bytes_sent;
+            bot_stats.status_classes.add(entry.status_code);
+            if is_error_status(entry.status_code) {
+                *bot_stats.error_urls.entry(entry.url.clone()).or_insert(0) += 1;
+            }
             if entry.verified_googlebot {
                 bot_stats.verified_count += 1;
             } else if entry.bot_name.contains("Google") || entry.bot_name.contains("google") {
                 bot_stats.unverified_count += 1;
+                // Other Google crawlers and fetchers use ranges outside Googlebot's
+                if entry.bot_name == "Googlebot" {
+                    if let Some(ip) = entry.ips.first() {
+                        *self.spoofed_googlebot_ips.entry(ip.clone()).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            // Bot x URL crawl matrix
+            if let Some(capacity) = heavy_hitters {
+                self.bot_url_heavy_hitters
+                    .entry(entry.bot_name.clone())
+                    .or_insert_with(|| SpaceSaving::new(capacity))
+                    .insert(&entry.url);
+            } else {
+                let bot_url = self
+                    .hits_by_bot_url
+                    .entry(entry.bot_name.clone())
+                    .or_default()
+                    .entry(entry.url.clone())
+                    .or_default();
+                bot_url.hits += 1;
+                bot_url.bytes_total += entry.bytes_sent;
+                *bot_url.status_codes.entry(entry.status_code).or_insert(0) += 1;
+                let domain_crawl = bot_url.by_domain.entry(entry.domain.clone()).or_default();
+                domain_crawl.hits += 1;
+                if let Some(dt) = entry.datetime {
+                    bot_url.seen.add(dt);
+                    bot_url.status_runs.add(entry.status_code, dt);
+                    domain_crawl.seen.add(dt);
+                }
             }
 
             // Category stats
             *self.hits_by_category.entry(entry.bot_category.clone()).or_insert(0) += 1;
+
+            // Query parameter crawl activity
+            self.query_params.add_entry(entry, &self.config);
+        }
+
+        // Googlebot crawler type stats
+        if !entry.googlebot_type.is_empty() {
+            *self.hits_by_googlebot_type.entry(entry.googlebot_type.clone()).or_insert(0) += 1;
         }
 
         // Time-based stats
@@ -131,6 +857,52 @@ impl Statistics {
             let date_key = dt.format("%Y-%m-%d").to_string();
             *self.hits_by_hour.entry(hour_key).or_insert(0) += 1;
             *self.hits_by_date.entry(date_key).or_insert(0) += 1;
+            let bucket = self.config.time_bucket.floor(dt.naive_local());
+            *self
+                .hits_by_time_bucket
+                .entry(self.config.time_bucket.format(bucket))
+                .or_insert(0) += 1;
+
+            let series = &mut self.time_series;
+            series.add("traffic", if entry.is_bot { "bot" } else { "human" }, bucket);
+            series.add("status_class", status_class_label(entry.status_code), bucket);
+            if entry.is_bot && !entry.bot_name.is_empty() {
+                series.add("bot", &entry.bot_name, bucket);
+                series.add("bot_category", &entry.bot_category, bucket);
+            }
+            if !entry.segment.is_empty() {
+                series.add("segment", &entry.segment, bucket);
+            }
+            if self.config.time_series_by_domain {
+                series.add("domain", &entry.domain, bucket);
+            }
+
+            let hour = TimeBucket::Hour.floor(dt.naive_utc());
+            self.hourly_offsets
+                .entry(hour)
+                .or_insert_with(|| dt.offset().local_minus_utc());
+            self.hourly_series.add("status_class", status_class_label(entry.status_code), hour);
+            if entry.is_bot && !entry.bot_name.is_empty() {
+                self.hourly_series.add("bot", &entry.bot_name, hour);
+            }
+
+            let heatmap_key = match (entry.is_bot, entry.bot_category.is_empty()) {
+                (false, _) => "human",
+                (true, true) => "unknown",
+                (true, false) => entry.bot_category.as_str(),
+            };
+            if let Some(heatmap) = self.activity_heatmaps.get_mut(heatmap_key) {
+                heatmap.add(dt.naive_local());
+            } else {
+                let mut heatmap = Heatmap::default();
+                heatmap.add(dt.naive_local());
+                self.activity_heatmaps.insert(heatmap_key.to_string(), heatmap);
+            }
+            if self.latest_timestamp.is_none_or(|latest| dt > latest) {
+                self.latest_timestamp = Some(dt);
+            }
+        } else {
+            self.unparsed_timestamps += 1;
         }
 
         // Method stats
@@ -139,8 +911,99 @@ impl Statistics {
         // Domain stats
         *self.hits_by_domain.entry(entry.domain.clone()).or_insert(0) += 1;
 
-        // Server stats
-        *self.hits_by_server.entry(entry.server.clone()).or_insert(0) += 1;
+        // Server stats
+        *self.hits_by_server.entry(entry.server.clone()).or_insert(0) += 1;
+
+        // GeoIP stats
+        if !entry.country.is_empty() {
+            self.hits_by_country.entry(entry.country.clone()).or_default().add(entry.is_bot);
+        }
+        if let Some(asn) = entry.asn {
+            let asn_stats = self.hits_by_asn.entry(asn).or_default();
+            if asn_stats.organization.is_empty() {
+                asn_stats.organization = entry.as_org.clone();
+            }
+            asn_stats.traffic.add(entry.is_bot);
+        }
+
+        // Resource type stats
+        if !entry.resource_type.is_empty() {
+            self.hits_by_resource_type
+                .entry(entry.resource_type.clone())
+                .or_default()
+                .add_entry(entry);
+        }
+
+        // Page type stats
+        if !entry.segment.is_empty() {
+            self.hits_by_segment
+                .entry(entry.segment.clone())
+                .or_insert_with(|| SegmentStats {
+                    unique_urls: self.config.unique_counter(),
+                    ..Default::default()
+                })
+                .add_entry(entry);
+        }
+
+        // robots.txt compliance
+        if let Some(allowed) = entry.robots_allowed {
+            self.robots.add_entry(entry, allowed);
+        }
+
+        // Attack probes
+        if !entry.threat_category.is_empty() {
+            self.attacks.add_entry(entry);
+        }
+
+        // Per-client request rate buckets
+        if let Some(rate) = &self.config.rate {
+            self.rate_windows.add_entry(entry, rate);
+        }
+
+        // Response time sketches
+        if let Some(response_time) = entry.response_time {
+            self.latency.add(response_time);
+            if heavy_hitters.is_none() {
+                self.latency_by_url.entry(entry.url.clone()).or_default().add(response_time);
+            }
+            if entry.is_bot && !entry.bot_name.is_empty() {
+                self.latency_by_bot.entry(entry.bot_name.clone()).or_default().add(response_time);
+            }
+            self.latency_by_status_class
+                .entry(status_class_label(entry.status_code).to_string())
+                .or_default()
+                .add(response_time);
+            if let Some(dt) = entry.datetime {
+                self.latency_by_hour
+                    .entry(dt.format("%Y-%m-%d %H:00").to_string())
+                    .or_default()
+                    .add(response_time);
+            }
+        }
+
+        if self.total_requests.is_multiple_of(PRUNE_INTERVAL) {
+            self.prune();
+        }
+    }
+
+    /// In heavy hitters mode, cut the remaining per-URL and per-IP maps back to
+    /// their heaviest keys so memory stays bounded
+    fn prune(&mut self) {
+        let Some(capacity) = self.config.heavy_hitters else {
+            return;
+        };
+        for bot_stats in self.hits_by_bot.values_mut() {
+            keep_heaviest(&mut bot_stats.error_urls, capacity, |count| *count);
+        }
+        keep_heaviest(&mut self.attacks.by_url, capacity, |count| *count);
+        keep_heaviest(&mut self.attacks.by_ip, capacity, |attacker| attacker.hits);
+        keep_heaviest(&mut self.spoofed_googlebot_ips, capacity, |count| *count);
+        keep_heaviest(&mut self.query_params.by_param, capacity, |param| param.bot_hits);
+        keep_heaviest(&mut self.query_params.combinations, capacity, |count| *count);
+        for robots_stats in self.robots.by_bot.values_mut() {
+            keep_heaviest(&mut robots_stats.disallowed_urls, capacity, |count| *count);
+        }
+        keep_heaviest(&mut self.robots.blocked_human_urls, capacity, |count| *count);
     }
 
     /// Merge two Statistics objects (for parallel processing)
@@ -152,8 +1015,8 @@ impl Statistics {
         self.verified_googlebot_requests += other.verified_googlebot_requests;
 
         // Merge sets
-        self.unique_urls.extend(other.unique_urls);
-        self.unique_ips.extend(other.unique_ips);
+        self.unique_urls.merge(&other.unique_urls);
+        self.unique_ips.merge(&other.unique_ips);
 
         // Merge status distribution
         for (code, count) in other.status_distribution {
@@ -165,16 +1028,38 @@ impl Statistics {
             self.hits_by_url.entry(url).or_default().merge(&stats);
         }
 
+        for (url, variants) in other.url_variants {
+            match self.url_variants.entry(url) {
+                Entry::Occupied(mut mine) => mine.get_mut().merge(&variants),
+                Entry::Vacant(slot) => {
+                    slot.insert(variants);
+                }
+            }
+        }
+
         // Merge bot stats
         for (bot, stats) in other.hits_by_bot {
             self.hits_by_bot.entry(bot).or_default().merge(&stats);
         }
 
+        // Merge bot x URL crawl matrix
+        for (bot, urls) in other.hits_by_bot_url {
+            let target = self.hits_by_bot_url.entry(bot).or_default();
+            for (url, stats) in urls {
+                target.entry(url).or_default().merge(&stats);
+            }
+        }
+
         // Merge category stats
         for (category, count) in other.hits_by_category {
             *self.hits_by_category.entry(category).or_insert(0) += count;
         }
 
+        // Merge Googlebot crawler type stats
+        for (crawler_type, count) in other.hits_by_googlebot_type {
+            *self.hits_by_googlebot_type.entry(crawler_type).or_insert(0) += count;
+        }
+
         // Merge time-based stats
         for (hour, count) in other.hits_by_hour {
             *self.hits_by_hour.entry(hour).or_insert(0) += count;
@@ -182,6 +1067,23 @@ impl Statistics {
         for (date, count) in other.hits_by_date {
             *self.hits_by_date.entry(date).or_insert(0) += count;
         }
+        for (bucket, count) in other.hits_by_time_bucket {
+            *self.hits_by_time_bucket.entry(bucket).or_insert(0) += count;
+        }
+        self.unparsed_timestamps += other.unparsed_timestamps;
+        self.time_series.merge(&other.time_series);
+        self.hourly_series.merge(&other.hourly_series);
+        for (hour, offset) in other.hourly_offsets {
+            self.hourly_offsets.entry(hour).or_insert(offset);
+        }
+        for (key, heatmap) in other.activity_heatmaps {
+            self.activity_heatmaps.entry(key).or_default().merge(&heatmap);
+        }
+        if let Some(dt) = other.latest_timestamp {
+            if self.latest_timestamp.is_none_or(|latest| dt > latest) {
+                self.latest_timestamp = Some(dt);
+            }
+        }
 
         // Merge method stats
         for (method, count) in other.hits_by_method {
@@ -198,8 +1100,115 @@ impl Statistics {
             *self.hits_by_server.entry(server).or_insert(0) += count;
         }
 
+        // Merge GeoIP stats
+        for (country, split) in other.hits_by_country {
+            self.hits_by_country.entry(country).or_default().merge(&split);
+        }
+        for (asn, stats) in other.hits_by_asn {
+            self.hits_by_asn.entry(asn).or_default().merge(&stats);
+        }
+
+        // Merge attack stats
+        self.attacks.merge(&other.attacks);
+
+        // Merge query parameter stats
+        self.query_params.merge(&other.query_params);
+
+        // Merge resource type stats
+        for (resource_type, stats) in other.hits_by_resource_type {
+            self.hits_by_resource_type.entry(resource_type).or_default().merge(&stats);
+        }
+
+        // Merge segment stats
+        for (segment, stats) in other.hits_by_segment {
+            self.hits_by_segment.entry(segment).or_default().merge(&stats);
+        }
+
+        self.robots.merge(&other.robots);
+
+        for (ip, count) in other.spoofed_googlebot_ips {
+            *self.spoofed_googlebot_ips.entry(ip).or_insert(0) += count;
+        }
+
+        // Merge rate buckets
+        self.rate_windows.merge(&other.rate_windows);
+
+        // Merge latency sketches
+        self.latency.merge(&other.latency);
+        for (target, source) in [
+            (&mut self.latency_by_url, other.latency_by_url),
+            (&mut self.latency_by_bot, other.latency_by_bot),
+            (&mut self.latency_by_status_class, other.latency_by_status_class),
+            (&mut self.latency_by_hour, other.latency_by_hour),
+        ] {
+            for (key, sketch) in source {
+                target.entry(key).or_default().merge(&sketch);
+            }
+        }
+
+        // Merge heavy hitters summaries
+        match (&mut self.url_heavy_hitters, other.url_heavy_hitters) {
+            (Some(mine), Some(theirs)) => mine.merge(&theirs),
+            (mine @ None, theirs) => *mine = theirs,
+            _ => {}
+        }
+        for (bot, summary) in other.bot_url_heavy_hitters {
+            match self.bot_url_heavy_hitters.entry(bot) {
+                Entry::Occupied(mut mine) => mine.get_mut().merge(&summary),
+                Entry::Vacant(slot) => {
+                    slot.insert(summary);
+                }
+            }
+        }
+
+        self.prune();
         self
     }
+
+    /// Crawl matrix rows for one bot, with days since each URL was last crawled
+    /// relative to the newest timestamp in the logs, sorted by hits
+    pub fn crawl_recency(&self, bot: &str) -> Vec<BotUrlRow> {
+        let mut rows: Vec<BotUrlRow> = self
+            .hits_by_bot_url
+            .get(bot)
+            .into_iter()
+            .flatten()
+            .map(|(url, stats)| BotUrlRow {
+                url: url.clone(),
+                days_since_last_crawl: match (self.latest_timestamp, stats.seen.last_seen) {
+                    (Some(latest), Some(last)) => Some((latest - last).num_days()),
+                    _ => None,
+                },
+                stats: stats.clone(),
+            })
+            .collect();
+        rows.sort_by(|a, b| b.stats.hits.cmp(&a.stats.hits).then_with(|| a.url.cmp(&b.url)));
+        rows
+    }
+
+    /// URLs crawled by Googlebot exclusively with the given crawler type
+    /// (e.g. "Desktop"), sorted by hits
+    pub fn urls_crawled_only_by(&self, googlebot_type: &str) -> Vec<(&String, &UrlStats)> {
+        let mut urls: Vec<_> = self
+            .hits_by_url
+            .iter()
+            .filter(|(_, stats)| {
+                stats.googlebot_types.contains_key(googlebot_type)
+                    && stats.googlebot_types.len() == 1
+            })
+            .collect();
+        urls.sort_by(|a, b| b.1.hits.cmp(&a.1.hits).then_with(|| a.0.cmp(b.0)));
+        urls
+    }
+
+    /// Clients exceeding the configured request rates; empty unless rate windows
+    /// are collected
+    pub fn bursts(&self) -> Vec<Burst> {
+        match &self.config.rate {
+            Some(rate) => self.rate_windows.detect_bursts(rate),
+            None => Vec::new(),
+        }
+    }
 }
 
 /// Summary statistics for JSON output (without large HashSets serialized as arrays)
@@ -209,19 +1218,151 @@ pub struct StatisticsSummary {
     pub total_bytes: u64,
     pub unique_urls_count: usize,
     pub unique_ips_count: usize,
+    /// Standard error of the unique counts when approximate counting is enabled
+    pub unique_counts_error: Option<f64>,
     pub bot_requests: u64,
     pub human_requests: u64,
     pub verified_googlebot_requests: u64,
     pub bot_percentage: f64,
     pub status_distribution: HashMap<u16, u64>,
     pub top_urls: Vec<(String, UrlStats)>,
+    /// Per-URL stats rolled up by directory, the busiest `top_n` x `path_depth` directories
+    pub path_tree: PathNode,
+    /// Flat table of the path tree's sections, busiest first
+    pub sections: Vec<SectionRow>,
+    /// Normalized URLs that several raw URLs collapsed into, most variants first
+    pub collapsed_urls: Vec<CollapsedUrl>,
     pub top_bots: Vec<(String, BotStatsSummary)>,
+    pub bot_url_matrix: HashMap<String, Vec<BotUrlRow>>,
     pub hits_by_category: HashMap<String, u64>,
+    pub hits_by_googlebot_type: HashMap<String, u64>,
+    pub desktop_only_urls: Vec<String>,
     pub hits_by_hour: HashMap<String, u64>,
     pub hits_by_date: HashMap<String, u64>,
+    /// Bucket size of `hits_by_time_bucket` (minute, 5m, 15m, hour, day, week, month)
+    pub time_bucket: &'static str,
+    pub hits_by_time_bucket: HashMap<String, u64>,
+    pub unparsed_timestamps: u64,
+    pub time_series: TimeSeriesColumns,
+    /// Spikes and drops in the hourly and daily series per bot and status class
+    pub anomalies: Vec<Anomaly>,
+    /// Bot category (or `human`) -> 7x24 hits, Monday first, in the configured timezone
+    pub activity_heatmaps: HashMap<String, Heatmap>,
     pub hits_by_method: HashMap<String, u64>,
     pub hits_by_domain: HashMap<String, u64>,
     pub hits_by_server: HashMap<String, u64>,
+    pub hits_by_country: HashMap<String, TrafficSplit>,
+    pub top_asns: Vec<(u32, AsnStats)>,
+    pub attacks: AttackSummary,
+    pub query_params: QueryParamSummary,
+    pub segments: Vec<(String, SegmentSummary)>,
+    pub hits_by_resource_type: HashMap<String, ResourceTypeStats>,
+    pub robots: RobotsSummary,
+    pub bursts: Vec<Burst>,
+    pub status_inconsistencies: Vec<StatusInconsistency>,
+    pub bot_status_inconsistencies: Vec<StatusInconsistency>,
+    pub latency: Option<LatencySummary>,
+    pub slowest_urls: Vec<(String, LatencySummary)>,
+    pub latency_by_bot: HashMap<String, LatencySummary>,
+    pub latency_by_status_class: HashMap<String, LatencySummary>,
+    pub latency_by_hour: HashMap<String, LatencySummary>,
+    /// Approximate top URLs with error bounds, in heavy hitters mode
+    pub top_urls_approx: Option<HeavyHittersSummary>,
+    pub top_urls_by_bot_approx: HashMap<String, HeavyHittersSummary>,
+    /// Crawl coverage of the sitemap, filled in when a sitemap is given
+    pub sitemap: Option<SitemapCoverage>,
+    /// Crawl export joined onto the URL stats, filled in when a crawl CSV is given
+    pub crawl: Option<CrawlReport>,
+}
+
+/// One row of the bot x URL crawl matrix
+#[derive(Debug, Clone, Serialize)]
+pub struct BotUrlRow {
+    pub url: String,
+    #[serde(flatten)]
+    pub stats: BotUrlStats,
+    pub days_since_last_crawl: Option<i64>,
+}
+
+/// A normalized URL and the raw variants counted under it
+#[derive(Debug, Clone, Serialize)]
+pub struct CollapsedUrl {
+    pub url: String,
+    /// Distinct raw spellings, capped at the number tracked per URL
+    pub variant_count: usize,
+    pub hits: u64,
+    pub top_variants: Vec<(String, u64)>,
+}
+
+/// Per page type traffic, with bot crawl frequency over the days in the logs
+#[derive(Debug, Serialize)]
+pub struct SegmentSummary {
+    pub hits: u64,
+    pub bot_hits: u64,
+    pub human_hits: u64,
+    pub bytes_total: u64,
+    pub avg_bytes: f64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+    pub unique_urls_count: usize,
+    pub top_bots: Vec<(String, u64)>,
+    pub bot_hits_per_day: f64,
+    pub bot_hits_by_date: HashMap<String, u64>,
+}
+
+/// robots.txt compliance of one bot in the summary
+#[derive(Debug, Serialize)]
+pub struct RobotsBotSummary {
+    pub checked_hits: u64,
+    pub disallowed_hits: u64,
+    /// Share of checked hits on disallowed URLs, as a percentage
+    pub violation_rate: f64,
+    pub disallowed_url_count: usize,
+    pub top_disallowed_urls: Vec<(String, u64)>,
+}
+
+/// robots.txt compliance report, empty when no robots.txt was given
+#[derive(Debug, Serialize)]
+pub struct RobotsSummary {
+    /// Hits on disallowed URLs over every bot, before truncation to the top bots
+    pub disallowed_hits: u64,
+    /// Bots ranked by hits on disallowed URLs
+    pub bots: Vec<(String, RobotsBotSummary)>,
+    pub blocked_human_hits: u64,
+    pub blocked_urls_with_human_traffic_count: usize,
+    pub blocked_urls_with_human_traffic: Vec<(String, u64)>,
+}
+
+/// One query parameter in the parameter report
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryParamRow {
+    pub bot_hits: u64,
+    pub googlebot_hits: u64,
+    pub distinct_values: usize,
+    pub url_count: usize,
+}
+
+/// Bot crawl spent on parameterized URLs, with top parameters and combinations
+#[derive(Debug, Serialize)]
+pub struct QueryParamSummary {
+    pub parameterized_bot_hits: u64,
+    /// Percentage of bot hits on URLs with a query string
+    pub parameterized_bot_share: f64,
+    pub parameterized_googlebot_hits: u64,
+    /// Percentage of Googlebot hits on URLs with a query string
+    pub parameterized_googlebot_share: f64,
+    pub top_params: Vec<(String, QueryParamRow)>,
+    pub top_combinations: Vec<(String, u64)>,
+}
+
+/// Attack statistics summary with top attacking IPs and probed URLs
+#[derive(Debug, Serialize)]
+pub struct AttackSummary {
+    pub total: u64,
+    pub not_found: u64,
+    pub by_category: HashMap<String, u64>,
+    pub top_ips: Vec<(String, AttackerStats)>,
+    pub top_urls: Vec<(String, u64)>,
 }
 
 /// Bot stats summary without HashSet for serialization
@@ -232,6 +1373,12 @@ pub struct BotStatsSummary {
     pub unique_urls_count: usize,
     pub verified_count: u64,
     pub unverified_count: u64,
+    pub bytes_total: u64,
+    pub avg_bytes: f64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+    pub error_rate: f64,
+    pub error_urls: Vec<(String, u64)>,
 }
 
 impl Statistics {
@@ -239,20 +1386,62 @@ impl Statistics {
     pub fn to_summary(&self, top_n: usize) -> StatisticsSummary {
         // Sort URLs by hits
         let mut url_vec: Vec<_> = self.hits_by_url.iter().collect();
-        url_vec.sort_by(|a, b| b.1.hits.cmp(&a.1.hits));
+        url_vec.sort_by(|a, b| b.1.hits.cmp(&a.1.hits).then_with(|| a.0.cmp(b.0)));
         let top_urls: Vec<(String, UrlStats)> = url_vec
             .into_iter()
             .take(top_n)
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
 
+        // Directory rollup as a drill-down tree and a flat table
+        let mut path_tree = build_path_tree(&self.hits_by_url, self.config.path_depth);
+        let mut sections = path_tree.sections();
+        sections.truncate(top_n);
+        path_tree.prune(top_n * self.config.path_depth.max(1));
+
+        // Normalized URLs with more than one raw spelling
+        let mut collapsed_urls: Vec<CollapsedUrl> = self
+            .url_variants
+            .iter()
+            .filter_map(|(url, variants)| {
+                let hits = self.hits_by_url.get(url).map_or(0, |stats| stats.hits);
+                let unchanged = hits.saturating_sub(variants.total());
+                let mut top_variants: Vec<(String, u64)> = variants
+                    .top(top_n)
+                    .into_iter()
+                    .map(|variant| (variant.key, variant.count))
+                    .collect();
+                if unchanged > 0 {
+                    top_variants.push((url.clone(), unchanged));
+                    top_variants.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                    top_variants.truncate(top_n);
+                }
+                let variant_count = variants.len() + usize::from(unchanged > 0);
+                (variant_count > 1).then(|| CollapsedUrl {
+                    url: url.clone(),
+                    variant_count,
+                    hits,
+                    top_variants,
+                })
+            })
+            .collect();
+        collapsed_urls.sort_by(|a, b| {
+            b.variant_count
+                .cmp(&a.variant_count)
+                .then_with(|| b.hits.cmp(&a.hits))
+                .then_with(|| a.url.cmp(&b.url))
+        });
+        collapsed_urls.truncate(top_n);
+
         // Sort bots by hits
         let mut bot_vec: Vec<_> = self.hits_by_bot.iter().collect();
-        bot_vec.sort_by(|a, b| b.1.hits.cmp(&a.1.hits));
+        bot_vec.sort_by_key(|(_, v)| std::cmp::Reverse(v.hits));
         let top_bots: Vec<(String, BotStatsSummary)> = bot_vec
             .into_iter()
             .take(top_n)
             .map(|(k, v)| {
+                let error_urls = top_counts(&v.error_urls, top_n);
+
                 (
                     k.clone(),
                     BotStatsSummary {
@@ -261,11 +1450,196 @@ impl Statistics {
                         unique_urls_count: v.unique_urls.len(),
                         verified_count: v.verified_count,
                         unverified_count: v.unverified_count,
+                        bytes_total: v.bytes_total,
+                        avg_bytes: v.avg_bytes(),
+                        status_classes: v.status_classes,
+                        error_rate: v.error_rate(),
+                        error_urls,
+                    },
+                )
+            })
+            .collect();
+
+        // Approximate top URLs for the top bots, in heavy hitters mode
+        let top_urls_by_bot_approx: HashMap<String, HeavyHittersSummary> = top_bots
+            .iter()
+            .filter_map(|(bot, _)| {
+                let summary = self.bot_url_heavy_hitters.get(bot)?;
+                Some((bot.clone(), summary.summary(top_n)))
+            })
+            .collect();
+
+        // Crawl matrix for the top bots
+        let bot_url_matrix: HashMap<String, Vec<BotUrlRow>> = top_bots
+            .iter()
+            .map(|(bot, _)| {
+                let mut rows = self.crawl_recency(bot);
+                rows.truncate(top_n);
+                (bot.clone(), rows)
+            })
+            .collect();
+
+        // Pages Googlebot only fetched with its desktop crawler
+        let desktop_only_urls: Vec<String> = self
+            .urls_crawled_only_by("Desktop")
+            .into_iter()
+            .take(top_n)
+            .map(|(url, _)| url.clone())
+            .collect();
+
+        // Sort networks by hits
+        let mut asn_vec: Vec<_> = self.hits_by_asn.iter().collect();
+        asn_vec.sort_by_key(|(_, v)| std::cmp::Reverse(v.traffic.hits));
+        let top_asns: Vec<(u32, AsnStats)> = asn_vec
+            .into_iter()
+            .take(top_n)
+            .map(|(k, v)| (*k, v.clone()))
+            .collect();
+
+        // Sort attackers and probed URLs by hits
+        let mut attacker_vec: Vec<_> = self.attacks.by_ip.iter().collect();
+        attacker_vec.sort_by_key(|(_, v)| std::cmp::Reverse(v.hits));
+        let mut probed_vec: Vec<_> = self.attacks.by_url.iter().collect();
+        probed_vec.sort_by_key(|(_, v)| std::cmp::Reverse(**v));
+        let attacks = AttackSummary {
+            total: self.attacks.total,
+            not_found: self.attacks.not_found,
+            by_category: self.attacks.by_category.clone(),
+            top_ips: attacker_vec
+                .into_iter()
+                .take(top_n)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            top_urls: probed_vec
+                .into_iter()
+                .take(top_n)
+                .map(|(k, v)| (k.clone(), *v))
+                .collect(),
+        };
+
+        // Query parameters ranked by bot hits
+        let params = &self.query_params;
+        let mut param_vec: Vec<_> = params.by_param.iter().collect();
+        param_vec.sort_by(|a, b| b.1.bot_hits.cmp(&a.1.bot_hits).then_with(|| a.0.cmp(b.0)));
+        let query_params = QueryParamSummary {
+            parameterized_bot_hits: params.parameterized_bot_hits,
+            parameterized_bot_share: share(params.parameterized_bot_hits, params.bot_hits),
+            parameterized_googlebot_hits: params.parameterized_googlebot_hits,
+            parameterized_googlebot_share: share(
+                params.parameterized_googlebot_hits,
+                params.googlebot_hits,
+            ),
+            top_params: param_vec
+                .into_iter()
+                .take(top_n)
+                .map(|(name, stats)| {
+                    (
+                        name.clone(),
+                        QueryParamRow {
+                            bot_hits: stats.bot_hits,
+                            googlebot_hits: stats.googlebot_hits,
+                            distinct_values: stats.values.len(),
+                            url_count: stats.urls.len(),
+                        },
+                    )
+                })
+                .collect(),
+            top_combinations: top_counts(&params.combinations, top_n),
+        };
+
+        // robots.txt violations, worst offenders first
+        let mut robots_bots: Vec<(String, RobotsBotSummary)> = self
+            .robots
+            .by_bot
+            .iter()
+            .map(|(bot, stats)| {
+                (
+                    bot.clone(),
+                    RobotsBotSummary {
+                        checked_hits: stats.checked_hits,
+                        disallowed_hits: stats.disallowed_hits,
+                        violation_rate: share(stats.disallowed_hits, stats.checked_hits),
+                        disallowed_url_count: stats.disallowed_urls.len(),
+                        top_disallowed_urls: top_counts(&stats.disallowed_urls, top_n),
+                    },
+                )
+            })
+            .collect();
+        robots_bots.sort_by(|a, b| {
+            b.1.disallowed_hits
+                .cmp(&a.1.disallowed_hits)
+                .then_with(|| b.1.checked_hits.cmp(&a.1.checked_hits))
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        robots_bots.truncate(top_n);
+        let robots = RobotsSummary {
+            disallowed_hits: self.robots.by_bot.values().map(|b| b.disallowed_hits).sum(),
+            bots: robots_bots,
+            blocked_human_hits: self.robots.blocked_human_hits,
+            blocked_urls_with_human_traffic_count: self.robots.blocked_human_urls.len(),
+            blocked_urls_with_human_traffic: top_counts(&self.robots.blocked_human_urls, top_n),
+        };
+
+        // Page types ranked by hits; crawl frequency over all days with traffic
+        let days = self.hits_by_date.len().max(1) as f64;
+        let mut segment_vec: Vec<_> = self.hits_by_segment.iter().collect();
+        segment_vec.sort_by(|a, b| b.1.hits.cmp(&a.1.hits).then_with(|| a.0.cmp(b.0)));
+        let segments: Vec<(String, SegmentSummary)> = segment_vec
+            .into_iter()
+            .map(|(segment, stats)| {
+                let mut top_bots: Vec<(String, u64)> =
+                    stats.hits_by_bot.iter().map(|(k, v)| (k.clone(), *v)).collect();
+                top_bots.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                top_bots.truncate(top_n);
+                (
+                    segment.clone(),
+                    SegmentSummary {
+                        hits: stats.hits,
+                        bot_hits: stats.bot_hits,
+                        human_hits: stats.human_hits,
+                        bytes_total: stats.bytes_total,
+                        avg_bytes: stats.bytes_total as f64 / stats.hits.max(1) as f64,
+                        status_classes: stats.status_classes,
+                        unique_urls_count: stats.unique_urls.len(),
+                        top_bots,
+                        bot_hits_per_day: stats.bot_hits as f64 / days,
+                        bot_hits_by_date: stats.bot_hits_by_date.clone(),
                     },
                 )
             })
             .collect();
 
+        // Traffic anomalies, most severe first
+        let mut anomalies = detect_anomalies(
+            &self.hourly_series,
+            &self.hourly_offsets,
+            top_n,
+            &self.config.anomaly,
+        );
+        anomalies.truncate(top_n);
+
+        // Clients exceeding the configured request rates
+        let mut bursts = self.bursts();
+        bursts.truncate(top_n);
+
+        // URLs answering with mixed status classes, worst first
+        let mut status_inconsistencies = find_inconsistencies(&self.hits_by_url);
+        status_inconsistencies.truncate(top_n);
+        let mut bot_status_inconsistencies = find_bot_inconsistencies(&self.hits_by_bot_url);
+        bot_status_inconsistencies.truncate(top_n);
+
+        // Latency percentiles; URLs ranked by p90
+        let latency_map = |sketches: &HashMap<String, DdSketch>| -> HashMap<String, LatencySummary> {
+            sketches
+                .iter()
+                .filter_map(|(k, sketch)| sketch.summary().map(|s| (k.clone(), s)))
+                .collect()
+        };
+        let mut slowest_urls: Vec<(String, LatencySummary)> =
+            latency_map(&self.latency_by_url).into_iter().collect();
+        slowest_urls.sort_by(|a, b| b.1.p90.total_cmp(&a.1.p90).then_with(|| a.0.cmp(&b.0)));
+        slowest_urls.truncate(top_n);
+
         let bot_percentage = if self.total_requests > 0 {
             (self.bot_requests as f64 / self.total_requests as f64) * 100.0
         } else {
@@ -277,19 +1651,51 @@ impl Statistics {
             total_bytes: self.total_bytes,
             unique_urls_count: self.unique_urls.len(),
             unique_ips_count: self.unique_ips.len(),
+            unique_counts_error: self.unique_urls.standard_error(),
             bot_requests: self.bot_requests,
             human_requests: self.human_requests,
             verified_googlebot_requests: self.verified_googlebot_requests,
             bot_percentage,
             status_distribution: self.status_distribution.clone(),
             top_urls,
+            path_tree,
+            sections,
+            collapsed_urls,
             top_bots,
+            bot_url_matrix,
             hits_by_category: self.hits_by_category.clone(),
+            hits_by_googlebot_type: self.hits_by_googlebot_type.clone(),
+            desktop_only_urls,
             hits_by_hour: self.hits_by_hour.clone(),
             hits_by_date: self.hits_by_date.clone(),
+            time_bucket: self.config.time_bucket.label(),
+            hits_by_time_bucket: self.hits_by_time_bucket.clone(),
+            unparsed_timestamps: self.unparsed_timestamps,
+            time_series: self.time_series.to_columns(self.config.time_bucket, top_n),
+            anomalies,
+            activity_heatmaps: self.activity_heatmaps.clone(),
             hits_by_method: self.hits_by_method.clone(),
             hits_by_domain: self.hits_by_domain.clone(),
             hits_by_server: self.hits_by_server.clone(),
+            hits_by_country: self.hits_by_country.clone(),
+            top_asns,
+            attacks,
+            query_params,
+            segments,
+            hits_by_resource_type: self.hits_by_resource_type.clone(),
+            robots,
+            bursts,
+            status_inconsistencies,
+            bot_status_inconsistencies,
+            latency: self.latency.summary(),
+            slowest_urls,
+            latency_by_bot: latency_map(&self.latency_by_bot),
+            latency_by_status_class: latency_map(&self.latency_by_status_class),
+            latency_by_hour: latency_map(&self.latency_by_hour),
+            top_urls_approx: self.url_heavy_hitters.as_ref().map(|s| s.summary(top_n)),
+            top_urls_by_bot_approx,
+            sitemap: None,
+            crawl: None,
         }
     }
 }
@@ -297,22 +1703,545 @@ impl Statistics {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{test_line, TestLine};
+
+    fn stats_for(pipeline: &crate::Pipeline, lines: &[String]) -> Statistics {
+        let mut stats = pipeline.new_statistics();
+        for line in lines {
+            stats.add_entry(&pipeline.process_line(line).unwrap());
+        }
+        stats
+    }
+
+    /// Feed `lines` to one `Statistics` and, split in half, to two that get merged.
+    /// Both must give the same summary; returns the merged statistics.
+    fn merged_stats(pipeline: &crate::Pipeline, lines: &[String]) -> Statistics {
+        let (left, right) = lines.split_at(lines.len() / 2);
+        let merged = stats_for(pipeline, left).merge(stats_for(pipeline, right));
+        let summary = |stats: &Statistics| serde_json::to_value(stats.to_summary(10)).unwrap();
+        assert_eq!(summary(&merged), summary(&stats_for(pipeline, lines)));
+        merged
+    }
 
     #[test]
     fn test_statistics_merge() {
         let mut stats1 = Statistics::new();
         stats1.total_requests = 100;
         stats1.bot_requests = 30;
-        stats1.unique_urls.insert("/page1".to_string());
+        stats1.unique_urls.insert("/page1");
 
         let mut stats2 = Statistics::new();
         stats2.total_requests = 50;
         stats2.bot_requests = 20;
-        stats2.unique_urls.insert("/page2".to_string());
+        stats2.unique_urls.insert("/page2");
 
         let merged = stats1.merge(stats2);
         assert_eq!(merged.total_requests, 150);
         assert_eq!(merged.bot_requests, 50);
         assert_eq!(merged.unique_urls.len(), 2);
     }
+
+    #[test]
+    fn test_approximate_unique_counts() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.stats_config.approx_unique_error = Some(0.01);
+        let lines: Vec<String> = (0..1000)
+            .map(|i| {
+                TestLine::new("example.com", &format!("/p/{}", i), 200, "bingbot/2.0")
+                    .ip(&format!("10.0.{}.{}", i / 256, i % 256))
+                    .to_string()
+            })
+            .collect();
+
+        let summary = merged_stats(&pipeline, &lines).to_summary(10);
+        assert!(summary.unique_counts_error.is_some());
+        assert!((summary.unique_urls_count as f64 - 1000.0).abs() < 30.0);
+        assert!((summary.unique_ips_count as f64 - 1000.0).abs() < 30.0);
+        assert!((summary.top_bots[0].1.unique_urls_count as f64 - 1000.0).abs() < 30.0);
+    }
+
+    #[test]
+    fn test_urls_crawled_only_by_desktop() {
+        let desktop = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let mobile = "Mozilla/5.0 (Linux; Android 6.0.1; Nexus 5X Build/MMB29P) AppleWebKit/537.36 Chrome/120.0 Mobile Safari/537.36 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+
+        let mut stats = Statistics::new();
+        for (url, ua) in [("/desktop-only", desktop), ("/both", mobile), ("/both", desktop)] {
+            stats.add_entry(&crate::parse_line(&test_line("example.com", url, 200, ua)).unwrap());
+        }
+
+        let urls: Vec<_> = stats
+            .urls_crawled_only_by("Desktop")
+            .into_iter()
+            .map(|(url, _)| url.as_str())
+            .collect();
+        assert_eq!(urls, vec!["/desktop-only"]);
+        assert_eq!(stats.hits_by_googlebot_type["Desktop"], 2);
+        assert_eq!(stats.hits_by_googlebot_type["Smartphone"], 1);
+        assert_eq!(stats.hits_by_url["/both"].googlebot_types.len(), 2);
+    }
+
+    #[test]
+    fn test_bot_url_matrix() {
+        let line = |date: &str, url: &str, status: u16| {
+            TestLine::new("example.com", url, status, "Googlebot/2.1")
+                .time(&format!("{} +0000", date))
+                .to_string()
+        };
+
+        let mut stats1 = Statistics::new();
+        stats1.add_entry(&crate::parse_line(&line("01/Jan/2024:10:00:00", "/a", 200)).unwrap());
+        stats1.add_entry(&crate::parse_line(&line("05/Jan/2024:10:00:00", "/b", 200)).unwrap());
+        let mut stats2 = Statistics::new();
+        stats2.add_entry(&crate::parse_line(&line("03/Jan/2024:10:00:00", "/a", 404)).unwrap());
+        let merged = stats1.merge(stats2);
+
+        let a = &merged.hits_by_bot_url["Googlebot"]["/a"];
+        assert_eq!(a.hits, 2);
+        assert_eq!(a.bytes_total, 200);
+        assert_eq!(a.status_codes[&404], 1);
+        assert_eq!(a.seen.first_seen.unwrap().to_rfc3339(), "2024-01-01T10:00:00+00:00");
+        assert_eq!(a.seen.last_seen.unwrap().to_rfc3339(), "2024-01-03T10:00:00+00:00");
+
+        let rows = merged.crawl_recency("Googlebot");
+        assert_eq!(rows[0].url, "/a");
+        assert_eq!(rows[0].days_since_last_crawl, Some(2));
+        assert_eq!(rows[1].days_since_last_crawl, Some(0));
+    }
+
+    #[test]
+    fn test_bot_error_metrics() {
+        let line = |url: &str, status: u16, bytes: u64| {
+            TestLine::new("example.com", url, status, "bingbot/2.0")
+                .ip("40.77.167.1")
+                .bytes(bytes)
+                .to_string()
+        };
+
+        let mut stats1 = Statistics::new();
+        stats1.add_entry(&crate::parse_line(&line("/ok", 200, 1000)).unwrap());
+        stats1.add_entry(&crate::parse_line(&line("/moved", 301, 0)).unwrap());
+        let mut stats2 = Statistics::new();
+        stats2.add_entry(&crate::parse_line(&line("/gone", 404, 200)).unwrap());
+        stats2.add_entry(&crate::parse_line(&line("/broken", 500, 400)).unwrap());
+        let merged = stats1.merge(stats2);
+
+        let summary = merged.to_summary(10);
+        let (name, bing) = &summary.top_bots[0];
+        assert_eq!(name, "Bingbot");
+        assert_eq!(bing.bytes_total, 1600);
+        assert_eq!(bing.avg_bytes, 400.0);
+        assert_eq!(bing.status_classes.status_2xx, 1);
+        assert_eq!(bing.status_classes.status_3xx, 1);
+        assert_eq!(bing.status_classes.status_4xx, 1);
+        assert_eq!(bing.status_classes.status_5xx, 1);
+        assert_eq!(bing.error_rate, 50.0);
+        assert_eq!(bing.error_urls.len(), 2);
+    }
+
+    #[test]
+    fn test_latency_aggregation() {
+        let line = |ua: &str, status: u16, time: &str| {
+            TestLine::new("example.com", "/slow", status, ua)
+                .time("01/Jan/2024:10:00:00 +0000")
+                .extra(time)
+                .to_string()
+        };
+
+        let mut stats1 = Statistics::new();
+        stats1.add_entry(&crate::parse_line(&line("Googlebot/2.1", 200, "0.100")).unwrap());
+        stats1.add_entry(&crate::parse_line(&line("Mozilla/5.0", 200, "0.200")).unwrap());
+        let mut stats2 = Statistics::new();
+        stats2.add_entry(&crate::parse_line(&line("Googlebot/2.1", 503, "2.000")).unwrap());
+        let merged = stats1.merge(stats2);
+
+        let summary = merged.to_summary(10);
+        let overall = summary.latency.unwrap();
+        assert_eq!(overall.count, 3);
+        assert_eq!(overall.max, 2.0);
+        assert_eq!(summary.latency_by_bot["Googlebot"].count, 2);
+        assert_eq!(summary.latency_by_status_class["5xx"].count, 1);
+        assert_eq!(summary.latency_by_hour["2024-01-01 10:00"].count, 3);
+        assert_eq!(summary.slowest_urls[0].0, "/slow");
+        assert!((summary.latency_by_status_class["2xx"].p50 - 0.1).abs() < 0.002);
+    }
+
+    #[test]
+    fn test_heavy_hitters_mode() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.stats_config.heavy_hitters = Some(4);
+        let lines: Vec<String> = (0..40)
+            .map(|i| {
+                let (url, status) = if i % 3 == 0 {
+                    ("/popular".to_string(), 200)
+                } else {
+                    (format!("/item?sid={}", i), 404)
+                };
+                test_line("example.com", &url, status, "Googlebot/2.1")
+            })
+            .collect();
+        // Merged Space-Saving counters are approximate, so only their bounds are checked
+        let (left, right) = lines.split_at(20);
+        let merged = stats_for(&pipeline, left).merge(stats_for(&pipeline, right));
+        assert!(merged.hits_by_url.is_empty());
+        assert!(merged.hits_by_bot_url.is_empty());
+        assert!(merged.hits_by_bot["Googlebot"].error_urls.len() <= 8);
+
+        let summary = merged.to_summary(1);
+        let top = summary.top_urls_approx.unwrap();
+        assert_eq!(top.total, 40);
+        assert_eq!(top.items[0].key, "/popular");
+        assert!(top.items[0].guaranteed_count <= 14 && top.items[0].count >= 14);
+        assert_eq!(summary.top_urls_by_bot_approx["Googlebot"].items[0].key, "/popular");
+    }
+
+    #[test]
+    fn test_collapsed_url_variants() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.normalizer = Some(crate::normalizer::UrlNormalizer::default());
+        let urls = ["/page", "/page/", "/Page?utm_source=x", "/page#frag", "/%70age", "/page", "/other"];
+        let lines: Vec<String> = urls
+            .iter()
+            .map(|url| test_line("example.com", url, 200, "Mozilla/5.0"))
+            .collect();
+
+        let merged = merged_stats(&pipeline, &lines);
+        assert_eq!(merged.hits_by_url["/page"].hits, 6);
+        assert_eq!(merged.unique_urls.len(), 2);
+        // URLs normalization left alone get no variant counter
+        assert!(!merged.url_variants.contains_key("/other"));
+
+        let summary = merged.to_summary(10);
+        assert_eq!(summary.collapsed_urls.len(), 1);
+        let collapsed = &summary.collapsed_urls[0];
+        assert_eq!(collapsed.url, "/page");
+        assert_eq!(collapsed.variant_count, 5);
+        assert_eq!(collapsed.hits, 6);
+        assert_eq!(collapsed.top_variants[0], ("/page".to_string(), 2));
+    }
+
+    #[test]
+    fn test_url_variants_are_bounded() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.normalizer = Some(crate::normalizer::UrlNormalizer::default());
+        let mut stats = pipeline.new_statistics();
+        for i in 0..200 {
+            let url = format!("/page?utm_source={}", i);
+            let line = test_line("example.com", &url, 200, "Mozilla/5.0");
+            stats.add_entry(&pipeline.process_line(&line).unwrap());
+        }
+        let variants = &stats.url_variants["/page"];
+        assert_eq!(variants.len(), MAX_URL_VARIANTS);
+        assert_eq!(variants.total(), 200);
+    }
+
+    #[test]
+    fn test_query_param_analytics() {
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let lines: Vec<String> = [
+            ("/shoes?color=red&size=9", googlebot),
+            ("/shoes", googlebot),
+            ("/shoes?size=10&color=red", googlebot),
+            ("/shoes?color=blue&color=black", "bingbot/2.0"),
+            ("/shoes?color=green", "Mozilla/5.0"),
+        ]
+        .iter()
+        .map(|(url, ua)| test_line("example.com", url, 200, ua))
+        .collect();
+
+        let summary = merged_stats(&crate::Pipeline::new(), &lines).to_summary(10).query_params;
+        assert_eq!(summary.parameterized_bot_hits, 3);
+        assert_eq!(summary.parameterized_bot_share, 75.0);
+        assert!((summary.parameterized_googlebot_share - 200.0 / 3.0).abs() < 1e-9);
+
+        let (name, color) = &summary.top_params[0];
+        assert_eq!(name, "color");
+        assert_eq!(color.bot_hits, 3);
+        assert_eq!(color.googlebot_hits, 2);
+        assert_eq!(color.distinct_values, 3);
+        assert_eq!(color.url_count, 3);
+        assert_eq!(summary.top_combinations[0], ("color&size".to_string(), 2));
+    }
+
+    #[test]
+    fn test_segment_aggregation() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.segments = Some(
+            crate::segmenter::SegmentRules::parse("product  path  ^/p/\nsearch  path  ^/search").unwrap(),
+        );
+        let line = |day: u32, url: &str, ua: &str, status: u16| {
+            TestLine::new("example.com", url, status, ua)
+                .time(&format!("0{}/Jan/2024:10:00:00 +0000", day))
+                .bytes(500)
+                .to_string()
+        };
+
+        let lines = [
+            line(1, "/p/1", "Googlebot/2.1", 200),
+            line(1, "/p/2", "Mozilla/5.0", 200),
+            line(2, "/p/1", "bingbot/2.0", 404),
+            line(2, "/about", "Googlebot/2.1", 200),
+        ];
+        let summary = merged_stats(&pipeline, &lines).to_summary(10);
+
+        let names: Vec<_> = summary.segments.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["product", "other"]);
+        let product = &summary.segments[0].1;
+        assert_eq!(product.hits, 3);
+        assert_eq!(product.bot_hits, 2);
+        assert_eq!(product.unique_urls_count, 2);
+        assert_eq!(product.status_classes.status_4xx, 1);
+        assert_eq!(product.avg_bytes, 500.0);
+        assert_eq!(product.bot_hits_per_day, 1.0);
+        assert_eq!(product.top_bots.len(), 2);
+    }
+
+    #[test]
+    fn test_robots_compliance() {
+        let mut robots = crate::robots::RobotsRules::default();
+        robots.insert(
+            "example.com",
+            crate::robots::RobotsTxt::parse(
+                "User-agent: *\nDisallow: /cart\n\nUser-agent: Googlebot\nDisallow: /search",
+            ),
+        );
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.robots = Some(robots);
+        // Normalization must not hide the requested URL from the rules
+        pipeline.normalizer = Some(crate::normalizer::UrlNormalizer::default());
+        let line = |domain: &str, url: &str, ua: &str| test_line(domain, url, 200, ua);
+
+        let mut stats = pipeline.new_statistics();
+        for entry in [
+            line("example.com", "/Search?q=a", "Googlebot/2.1"),
+            line("example.com", "/search?q=b", "Googlebot/2.1"),
+            line("example.com", "/cart", "Googlebot/2.1"),
+            line("example.com", "/cart", "bingbot/2.0"),
+            line("example.com", "/search?q=c", "Mozilla/5.0"),
+            line("example.com", "/cart", "Mozilla/5.0"),
+            line("other.org", "/cart", "bingbot/2.0"),
+        ] {
+            stats.add_entry(&pipeline.process_line(&entry).unwrap());
+        }
+        let summary = stats.to_summary(10).robots;
+
+        let (name, googlebot) = &summary.bots[0];
+        assert_eq!(name, "Googlebot");
+        assert_eq!(googlebot.checked_hits, 3);
+        assert_eq!(googlebot.disallowed_hits, 1);
+        assert_eq!(googlebot.top_disallowed_urls, vec![("/search?q=b".to_string(), 1)]);
+        let (_, bingbot) = &summary.bots[1];
+        assert_eq!((bingbot.checked_hits, bingbot.disallowed_hits), (1, 1));
+        // The total covers the bots cut from the top list
+        assert_eq!(stats.to_summary(1).robots.disallowed_hits, 2);
+
+        // Only the URL Googlebot may not crawl counts as blocked for human traffic
+        assert_eq!(summary.blocked_human_hits, 1);
+        assert_eq!(summary.blocked_urls_with_human_traffic[0].0, "/search?q=c");
+    }
+
+    #[test]
+    fn test_resource_type_aggregation() {
+        let line = |url: &str, ua: &str, status: u16, bytes: u64| {
+            TestLine::new("example.com", url, status, ua).bytes(bytes).to_string()
+        };
+
+        let lines = [
+            line("/", "Googlebot/2.1", 200, 5000),
+            line("/app.js", "Googlebot/2.1", 200, 800),
+            line("/app.js", "Googlebot/2.1", 404, 200),
+            line("/logo.png", "Mozilla/5.0", 200, 100),
+        ];
+        let summary = merged_stats(&crate::Pipeline::new(), &lines).to_summary(10);
+
+        let js = &summary.hits_by_resource_type["js"];
+        assert_eq!(js.totals.hits, 2);
+        assert_eq!(js.totals.bytes_total, 1000);
+        assert_eq!(js.by_bot["Googlebot"].status_classes.status_4xx, 1);
+        assert_eq!(summary.hits_by_resource_type["html"].bot_hits, 1);
+        assert_eq!(summary.hits_by_resource_type["image"].human_hits, 1);
+        assert!(summary.hits_by_resource_type["image"].by_bot.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_timezone_and_time_buckets() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.timezone = Some(chrono_tz::Asia::Tokyo);
+        pipeline.stats_config.time_bucket = TimeBucket::FifteenMinutes;
+        let line = |time: &str| {
+            TestLine::new("example.com", "/", 200, "Mozilla/5.0")
+                .time(time)
+                .to_string()
+        };
+
+        // Servers logging in UTC and in Berlin time, plus a broken timestamp
+        let lines = [
+            line("31/Dec/2023:15:10:00 +0000"),
+            line("31/Dec/2023:16:14:59 +0100"),
+            line("not a timestamp"),
+        ];
+        let summary = merged_stats(&pipeline, &lines).to_summary(10);
+
+        assert_eq!(summary.hits_by_date["2024-01-01"], 2);
+        assert_eq!(summary.hits_by_hour["2024-01-01 00:00"], 2);
+        assert_eq!(summary.time_bucket, "15m");
+        assert_eq!(summary.hits_by_time_bucket["2024-01-01 00:00"], 2);
+        assert_eq!(summary.unparsed_timestamps, 1);
+    }
+
+    #[test]
+    fn test_time_series_dimensions() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.stats_config.time_bucket = TimeBucket::Day;
+        pipeline.stats_config.time_series_by_domain = true;
+        let line = |day: u32, ua: &str, status: u16| {
+            TestLine::new("example.com", "/", status, ua)
+                .time(&format!("0{}/Jan/2024:10:00:00 +0000", day))
+                .to_string()
+        };
+
+        let lines = [
+            line(1, "Googlebot/2.1", 200),
+            line(3, "Googlebot/2.1", 503),
+            line(3, "Mozilla/5.0", 200),
+        ];
+        let columns = merged_stats(&pipeline, &lines).to_summary(10).time_series;
+
+        assert_eq!(columns.timestamps, vec!["2024-01-01", "2024-01-02", "2024-01-03"]);
+        assert_eq!(columns.series["bot"]["Googlebot"], vec![1, 0, 1]);
+        assert_eq!(columns.series["status_class"]["5xx"], vec![0, 0, 1]);
+        assert_eq!(columns.series["traffic"]["human"], vec![0, 0, 1]);
+        assert_eq!(columns.series["domain"]["example.com"], vec![1, 0, 2]);
+        assert_eq!(columns.series["bot_category"].len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_anomalies_across_dst_transition() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.timezone = Some(chrono_tz::America::New_York);
+        let start = DateTime::parse_from_rfc3339("2024-03-09T00:00:00Z").unwrap();
+        // Steady Googlebot crawl over the spring-forward night, except for `stopped`
+        let crawl = |stopped: Option<i64>| {
+            let lines: Vec<String> = (0..72)
+                .filter(|hour| Some(*hour) != stopped)
+                .flat_map(|hour| {
+                    let at = start + chrono::Duration::hours(hour);
+                    let line = TestLine::new("example.com", "/", 200, "Googlebot/2.1")
+                        .time(&at.format("%d/%b/%Y:%H:%M:%S +0000").to_string())
+                        .to_string();
+                    std::iter::repeat_n(line, 60)
+                })
+                .collect();
+            merged_stats(&pipeline, &lines).to_summary(10).anomalies
+        };
+
+        // --fail-on-anomaly exits with status 3 whenever this list is not empty
+        assert!(crawl(None).is_empty());
+
+        // A real drop is still found, labelled in local time (EDT after the switch)
+        let anomalies = crawl(Some(36));
+        assert!(!anomalies.is_empty());
+        assert!(anomalies
+            .iter()
+            .all(|a| a.resolution == "hour" && a.timestamp == "2024-03-10 08:00"));
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_activity_heatmaps_use_timezone() {
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.timezone = Some(chrono_tz::America::Los_Angeles);
+        let line = |ua: &str| {
+            TestLine::new("example.com", "/", 200, ua)
+                .time("01/Jan/2024:03:00:00 +0000")
+                .to_string()
+        };
+
+        let lines = [line("Googlebot/2.1"), line("Mozilla/5.0"), line("Mozilla/5.0")];
+        let summary = merged_stats(&pipeline, &lines).to_summary(10);
+
+        // Monday 03:00 UTC is Sunday 19:00 in Los Angeles
+        let human = summary.activity_heatmaps["human"];
+        assert_eq!(human.0[6][19], 2);
+        assert_eq!(human.total(), 2);
+        assert_eq!(summary.activity_heatmaps.len(), 2);
+        assert!(summary.activity_heatmaps.values().all(|h| h.0[6][19] > 0));
+    }
+
+    #[test]
+    fn test_geoip_aggregation() {
+        let (country, asn) = crate::geoip::tests::write_test_dbs();
+        let mut pipeline = crate::Pipeline::new();
+        pipeline.geoip =
+            Some(crate::geoip::GeoIpEnricher::open(Some(country.path()), Some(asn.path())).unwrap());
+
+        let bot = test_line("example.com", "/", 200, "Googlebot/2.1");
+        let human = TestLine::new("example.com", "/", 200, "Mozilla/5.0").ip("88.198.1.1");
+        let scraper =
+            TestLine::new("example.com", "/", 200, "python-requests/2.31").ip("88.198.1.2");
+
+        let mut stats1 = Statistics::new();
+        stats1.add_entry(&pipeline.process_line(&bot).unwrap());
+        stats1.add_entry(&pipeline.process_line(&human.to_string()).unwrap());
+        let mut stats2 = Statistics::new();
+        stats2.add_entry(&pipeline.process_line(&scraper.to_string()).unwrap());
+        let merged = stats1.merge(stats2);
+
+        assert_eq!(merged.hits_by_country["US"].bot_hits, 1);
+        assert_eq!(merged.hits_by_country["DE"].hits, 2);
+        assert_eq!(merged.hits_by_country["DE"].human_hits, 1);
+        assert_eq!(merged.hits_by_asn[&24940].organization, "Hetzner Online GmbH");
+        assert_eq!(merged.hits_by_asn[&24940].traffic.bot_hits, 1);
+        assert_eq!(merged.hits_by_asn[&15169].traffic.hits, 1);
+    }
+
+    #[test]
+    fn test_rate_windows_only_when_configured() {
+        let config = StatsConfig {
+            rate: Some(RateConfig {
+                ip_threshold: 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut off = Statistics::new();
+        let mut on = Statistics::with_config(config);
+        for second in 0..3 {
+            let line = TestLine::new("example.com", "/", 200, "Mozilla/5.0")
+                .time(&format!("01/Jan/2024:12:00:0{} +0000", second))
+                .to_string();
+            off.add_entry(&crate::parse_line(&line).unwrap());
+            on.add_entry(&crate::parse_line(&line).unwrap());
+        }
+
+        assert!(off.rate_windows.by_ip.is_empty());
+        assert!(off.bursts().is_empty());
+        assert_eq!(on.bursts()[0].total_requests, 3);
+    }
+
+    #[test]
+    fn test_attack_aggregation() {
+        let mut stats = Statistics::new();
+        for (ip, url) in [
+            ("203.0.113.5", "/.env"),
+            ("203.0.113.5", "/wp-login.php"),
+            ("198.51.100.1", "/missing"),
+        ] {
+            let line = TestLine::new("example.com", url, 404, "Mozilla/5.0").ip(ip);
+            stats.add_entry(&crate::parse_line(&line.to_string()).unwrap());
+        }
+        let merged = Statistics::new().merge(stats);
+
+        assert_eq!(merged.attacks.total, 2);
+        assert_eq!(merged.attacks.not_found, 2);
+        assert_eq!(merged.attacks.by_category["config_probe"], 1);
+        assert_eq!(merged.attacks.by_category["cms_scan"], 1);
+        assert_eq!(merged.attacks.by_ip["203.0.113.5"].hits, 2);
+        assert_eq!(merged.attacks.by_url["/.env"], 1);
+
+        let summary = merged.to_summary(10);
+        assert_eq!(summary.attacks.top_ips[0].0, "203.0.113.5");
+    }
 }
@@ -0,0 +1,245 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::time_bucket::TimeBucket;
+use crate::time_series::{TimeSeries, TimeSeriesColumns};
+
+/// Scale factor making the MAD a consistent estimator of the standard deviation
+const MAD_SCALE: f64 = 1.4826;
+
+/// Options for anomaly detection on the hourly and daily series
+#[derive(Debug, Clone)]
+pub struct AnomalyConfig {
+    /// Preceding buckets used as the baseline for hourly series
+    pub hourly_window: usize,
+    /// Preceding buckets used as the baseline for daily series
+    pub daily_window: usize,
+    /// Robust z-score (deviation from the median in scaled MADs) that flags a bucket
+    pub threshold: f64,
+    /// Minimum absolute difference from the baseline, to ignore noise on small counts
+    pub min_change: f64,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        AnomalyConfig {
+            hourly_window: 24,
+            daily_window: 7,
+            threshold: 3.5,
+            min_change: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyKind {
+    Spike,
+    Drop,
+}
+
+/// How far outside the baseline an anomaly is, relative to the threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalySeverity {
+    /// Up to twice the threshold
+    Minor,
+    /// Up to four times the threshold
+    Major,
+    /// Beyond four times the threshold, or a series dropping to zero
+    Critical,
+}
+
+/// A bucket whose count deviates significantly from its rolling baseline
+#[derive(Debug, Clone, Serialize)]
+pub struct Anomaly {
+    /// `bot` or `status_class`
+    pub dimension: String,
+    /// Bot name or status class
+    pub series: String,
+    /// `hour` or `day`
+    pub resolution: &'static str,
+    /// Local hour for hourly anomalies, UTC date for daily ones
+    pub timestamp: String,
+    pub observed: u64,
+    /// Rolling median of the preceding buckets
+    pub expected: f64,
+    /// Robust z-score of the observed value
+    pub score: f64,
+    pub kind: AnomalyKind,
+    pub severity: AnomalySeverity,
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Indices of anomalous points with their expected value and robust z-score.
+/// The first and last points are never flagged, as they usually cover partial periods.
+pub fn detect_series(
+    counts: &[u64],
+    window: usize,
+    config: &AnomalyConfig,
+) -> Vec<(usize, f64, f64)> {
+    let window = window.max(2);
+    let min_points = window.div_ceil(2);
+    let mut found = Vec::new();
+
+    for i in 1..counts.len().saturating_sub(1) {
+        let start = i.saturating_sub(window);
+        if i - start < min_points {
+            continue;
+        }
+        let mut baseline: Vec<f64> = counts[start..i].iter().map(|&c| c as f64).collect();
+        let expected = median(&mut baseline);
+        let mut deviations: Vec<f64> = baseline.iter().map(|v| (v - expected).abs()).collect();
+        // Poisson noise floor so a flat baseline does not flag every small change
+        let sigma = (MAD_SCALE * median(&mut deviations))
+            .max(expected.sqrt())
+            .max(1.0);
+
+        let observed = counts[i] as f64;
+        let score = (observed - expected) / sigma;
+        if score.abs() >= config.threshold && (observed - expected).abs() >= config.min_change {
+            found.push((i, expected, score));
+        }
+    }
+    found
+}
+
+fn severity(score: f64, observed: u64, threshold: f64) -> AnomalySeverity {
+    let ratio = score.abs() / threshold;
+    if observed == 0 || ratio > 4.0 {
+        AnomalySeverity::Critical
+    } else if ratio > 2.0 {
+        AnomalySeverity::Major
+    } else {
+        AnomalySeverity::Minor
+    }
+}
+
+fn detect_columns(
+    columns: &TimeSeriesColumns,
+    resolution: &'static str,
+    window: usize,
+    config: &AnomalyConfig,
+) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    for (dimension, series) in &columns.series {
+        for (name, counts) in series {
+            for (i, expected, score) in detect_series(counts, window, config) {
+                anomalies.push(Anomaly {
+                    dimension: dimension.clone(),
+                    series: name.clone(),
+                    resolution,
+                    timestamp: columns.timestamps[i].clone(),
+                    observed: counts[i],
+                    expected,
+                    score,
+                    kind: if score > 0.0 {
+                        AnomalyKind::Spike
+                    } else {
+                        AnomalyKind::Drop
+                    },
+                    severity: severity(score, counts[i], config.threshold),
+                });
+            }
+        }
+    }
+    anomalies
+}
+
+/// Find spikes and drops in the UTC hourly series and its daily rollup, keeping the
+/// `top_n` series per dimension, most severe first. Hours are labelled in local time
+/// using `offsets` (UTC hour -> UTC offset in seconds), so DST changes do not leave
+/// gaps or doubled hours in the series.
+pub fn detect_anomalies(
+    hourly: &TimeSeries,
+    offsets: &BTreeMap<NaiveDateTime, i32>,
+    top_n: usize,
+    config: &AnomalyConfig,
+) -> Vec<Anomaly> {
+    let local_hour = |start: NaiveDateTime| {
+        // Gap-filled hours take the offset of the closest earlier hour with traffic
+        let offset = offsets
+            .range(..=start)
+            .next_back()
+            .or_else(|| offsets.iter().next())
+            .map_or(0, |(_, offset)| *offset);
+        TimeBucket::Hour.format(start + Duration::seconds(offset.into()))
+    };
+    let hourly_columns = hourly.to_columns_labelled(TimeBucket::Hour, top_n, local_hour);
+    let daily_columns = hourly
+        .rollup(TimeBucket::Day)
+        .to_columns(TimeBucket::Day, top_n);
+
+    let mut anomalies = detect_columns(&hourly_columns, "hour", config.hourly_window, config);
+    anomalies.extend(detect_columns(
+        &daily_columns,
+        "day",
+        config.daily_window,
+        config,
+    ));
+    anomalies.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| b.score.abs().total_cmp(&a.score.abs()))
+            .then_with(|| a.timestamp.cmp(&b.timestamp))
+    });
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_spike_and_drop() {
+        let config = AnomalyConfig::default();
+        let mut counts = vec![
+            100, 104, 98, 101, 97, 103, 99, 102, 100, 0, 101, 350, 99, 100,
+        ];
+        let found = detect_series(&counts, 7, &config);
+        let flagged: Vec<usize> = found.iter().map(|(i, _, _)| *i).collect();
+        assert_eq!(flagged, vec![9, 11]);
+        assert!(found[0].2 < 0.0 && found[1].2 > 0.0);
+        assert!((found[0].1 - 100.0).abs() <= 1.0);
+
+        // Small absolute changes on a quiet series are ignored
+        counts = vec![2, 1, 2, 2, 1, 2, 9, 2, 1];
+        assert!(detect_series(&counts, 7, &config).is_empty());
+    }
+
+    #[test]
+    fn test_detect_anomalies_severity() {
+        let mut hourly = TimeSeries::default();
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        for hour in 0..48 {
+            let at = start + chrono::Duration::hours(hour);
+            // Googlebot stops crawling for an hour
+            let googlebot = if hour == 30 { 0 } else { 60 };
+            for _ in 0..googlebot {
+                hourly.add("bot", "Googlebot", at);
+            }
+        }
+
+        let anomalies = detect_anomalies(&hourly, &BTreeMap::new(), 10, &AnomalyConfig::default());
+        assert_eq!(anomalies.len(), 1);
+        let drop = &anomalies[0];
+        assert_eq!(drop.timestamp, "2024-01-02 06:00");
+        assert_eq!(drop.resolution, "hour");
+        assert_eq!(drop.kind, AnomalyKind::Drop);
+        assert_eq!(drop.severity, AnomalySeverity::Critical);
+        assert_eq!(drop.expected, 60.0);
+    }
+}
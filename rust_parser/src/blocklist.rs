@@ -0,0 +1,349 @@
+use ipnetwork::IpNetwork;
+use std::fmt::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use crate::aggregator::Statistics;
+use crate::bot_detector::google_ip_ranges;
+
+/// Server configuration formats a blocklist can be rendered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlocklistFormat {
+    Nginx,
+    Apache,
+    Iptables,
+    Nftables,
+    Fail2ban,
+}
+
+impl FromStr for BlocklistFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nginx" => Ok(BlocklistFormat::Nginx),
+            "apache" => Ok(BlocklistFormat::Apache),
+            "iptables" => Ok(BlocklistFormat::Iptables),
+            "nftables" | "nft" => Ok(BlocklistFormat::Nftables),
+            "fail2ban" => Ok(BlocklistFormat::Fail2ban),
+            _ => Err(format!(
+                "unknown blocklist format {:?} (expected nginx, apache, iptables, nftables or fail2ban)",
+                s
+            )),
+        }
+    }
+}
+
+/// Sources of IPs that can be selected for a blocklist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlocklistSource {
+    /// Clients that sent attack probes
+    Attackers,
+    /// Clients that exceeded rate thresholds
+    Bursts,
+    /// Clients claiming to be Googlebot from outside Google's IP ranges
+    Spoofed,
+}
+
+impl FromStr for BlocklistSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "attackers" => Ok(BlocklistSource::Attackers),
+            "bursts" => Ok(BlocklistSource::Bursts),
+            "spoofed" => Ok(BlocklistSource::Spoofed),
+            _ => Err(format!(
+                "unknown blocklist source {:?} (expected attackers, bursts or spoofed)",
+                s
+            )),
+        }
+    }
+}
+
+/// Options for building a blocklist
+#[derive(Debug, Clone)]
+pub struct BlocklistOptions {
+    /// Merge adjacent addresses into the smallest set of covering CIDRs
+    pub collapse_cidrs: bool,
+    /// Networks that must never be blocked
+    pub allowlist: Vec<IpNetwork>,
+    /// Also never block Google's crawler ranges
+    pub allow_googlebot: bool,
+    /// nftables table and set names (`<family> <table>`, `<set>`)
+    pub nft_table: String,
+    pub nft_set: String,
+}
+
+impl Default for BlocklistOptions {
+    fn default() -> Self {
+        BlocklistOptions {
+            collapse_cidrs: false,
+            allowlist: Vec::new(),
+            allow_googlebot: true,
+            nft_table: "inet filter".to_string(),
+            nft_set: "blocklist".to_string(),
+        }
+    }
+}
+
+/// Collect client IPs from the selected sources
+pub fn select_ips(stats: &Statistics, sources: &[BlocklistSource]) -> Vec<String> {
+    let mut ips: Vec<String> = Vec::new();
+
+    for source in sources {
+        match source {
+            BlocklistSource::Attackers => ips.extend(stats.attacks.by_ip.keys().cloned()),
+            BlocklistSource::Bursts => {
+                ips.extend(stats.bursts().into_iter().map(|burst| burst.ip))
+            }
+            BlocklistSource::Spoofed => ips.extend(stats.spoofed_googlebot_ips.keys().cloned()),
+        }
+    }
+
+    ips.sort();
+    ips.dedup();
+    ips
+}
+
+/// Parse IPs/subnets, drop allowlisted ones and optionally collapse into CIDRs
+pub fn build_blocklist<'a, I>(entries: I, options: &BlocklistOptions) -> Vec<IpNetwork>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut allowlist = options.allowlist.clone();
+    if options.allow_googlebot {
+        allowlist.extend_from_slice(google_ip_ranges());
+    }
+
+    let mut networks: Vec<IpNetwork> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let entry = entry.trim();
+            entry
+                .parse::<IpNetwork>()
+                .ok()
+                .or_else(|| entry.parse::<IpAddr>().ok().map(IpNetwork::from))
+        })
+        .filter(|net| !allowlist.iter().any(|allowed| overlaps(allowed, net)))
+        .collect();
+
+    if options.collapse_cidrs {
+        networks = collapse(&networks);
+    } else {
+        networks.sort_by_key(|net| (net.is_ipv6(), net.network(), net.prefix()));
+        networks.dedup();
+    }
+    networks
+}
+
+fn overlaps(a: &IpNetwork, b: &IpNetwork) -> bool {
+    a.contains(b.network()) || b.contains(a.network())
+}
+
+/// Address range of a network as inclusive u128 bounds, with its bit width
+fn to_range(net: &IpNetwork) -> (u128, u128, u8) {
+    let (start, bits) = match net.network() {
+        IpAddr::V4(v4) => (u32::from(v4) as u128, 32),
+        IpAddr::V6(v6) => (u128::from(v6), 128),
+    };
+    let host_bits = (bits - net.prefix()) as u32;
+    let size_minus_one = if host_bits == 128 { u128::MAX } else { (1u128 << host_bits) - 1 };
+    (start, start + size_minus_one, bits)
+}
+
+fn to_network(start: u128, prefix: u8, bits: u8) -> IpNetwork {
+    let addr = if bits == 32 {
+        IpAddr::V4(Ipv4Addr::from(start as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(start))
+    };
+    IpNetwork::new(addr, prefix).expect("valid prefix")
+}
+
+/// Merge overlapping/adjacent networks and split the ranges back into minimal CIDRs
+fn collapse(networks: &[IpNetwork]) -> Vec<IpNetwork> {
+    let mut result = Vec::new();
+
+    for bits in [32u8, 128] {
+        let mut ranges: Vec<(u128, u128)> = networks
+            .iter()
+            .map(to_range)
+            .filter(|r| r.2 == bits)
+            .map(|(start, end, _)| (start, end))
+            .collect();
+        ranges.sort();
+
+        let mut merged: Vec<(u128, u128)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        for (mut start, end) in merged {
+            loop {
+                // Largest aligned block starting at `start` that fits in the range
+                let mut host_bits = start.trailing_zeros().min(bits as u32);
+                while host_bits > 0 && (host_bits >= 128 || start + ((1u128 << host_bits) - 1) > end) {
+                    host_bits -= 1;
+                }
+                result.push(to_network(start, bits - host_bits as u8, bits));
+
+                let block_end = if host_bits >= 128 { u128::MAX } else { start + ((1u128 << host_bits) - 1) };
+                if block_end >= end {
+                    break;
+                }
+                start = block_end + 1;
+            }
+        }
+    }
+
+    result
+}
+
+fn cidr(net: &IpNetwork) -> String {
+    let full = if net.is_ipv4() { 32 } else { 128 };
+    if net.prefix() == full {
+        net.ip().to_string()
+    } else {
+        net.to_string()
+    }
+}
+
+/// Render networks in the given server configuration format
+pub fn render_blocklist(networks: &[IpNetwork], format: BlocklistFormat, options: &BlocklistOptions) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Blocklist generated by log_parser ({} entries)", networks.len());
+
+    match format {
+        BlocklistFormat::Nginx => {
+            for net in networks {
+                let _ = writeln!(out, "deny {};", cidr(net));
+            }
+        }
+        BlocklistFormat::Apache => {
+            out.push_str("<RequireAll>\n    Require all granted\n");
+            for net in networks {
+                let _ = writeln!(out, "    Require not ip {}", cidr(net));
+            }
+            out.push_str("</RequireAll>\n");
+        }
+        BlocklistFormat::Iptables => {
+            for net in networks {
+                let command = if net.is_ipv4() { "iptables" } else { "ip6tables" };
+                let _ = writeln!(out, "{} -A INPUT -s {} -j DROP", command, cidr(net));
+            }
+        }
+        BlocklistFormat::Nftables => {
+            for (suffix, is_v4) in [("v4", true), ("v6", false)] {
+                let elements: Vec<String> =
+                    networks.iter().filter(|n| n.is_ipv4() == is_v4).map(cidr).collect();
+                if !elements.is_empty() {
+                    let _ = writeln!(
+                        out,
+                        "add element {} {}_{} {{ {} }}",
+                        options.nft_table,
+                        options.nft_set,
+                        suffix,
+                        elements.join(", ")
+                    );
+                }
+            }
+        }
+        BlocklistFormat::Fail2ban => {
+            // One address per line, e.g. for `fail2ban-client set <jail> banip $(cat list)`
+            for net in networks {
+                let _ = writeln!(out, "{}", cidr(net));
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestLine;
+
+    fn options() -> BlocklistOptions {
+        BlocklistOptions {
+            allow_googlebot: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collapse_cidrs() {
+        let opts = BlocklistOptions {
+            collapse_cidrs: true,
+            ..options()
+        };
+        let ips: Vec<String> = (0..=3).map(|i| format!("203.0.113.{}", i)).collect();
+        let mut input: Vec<&str> = ips.iter().map(String::as_str).collect();
+        input.extend(["203.0.113.4", "198.51.100.7", "2001:db8::1", "2001:db8::"]);
+
+        let nets: Vec<String> = build_blocklist(input, &opts).iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            nets,
+            vec!["198.51.100.7/32", "203.0.113.0/30", "203.0.113.4/32", "2001:db8::/127"]
+        );
+    }
+
+    #[test]
+    fn test_allowlist_and_googlebot() {
+        let opts = BlocklistOptions {
+            allowlist: vec!["10.0.0.0/8".parse().unwrap()],
+            ..Default::default()
+        };
+        let nets = build_blocklist(["10.1.2.3", "66.249.66.1", "192.0.2.1", "not-an-ip"], &opts);
+        assert_eq!(nets, vec!["192.0.2.1/32".parse::<IpNetwork>().unwrap()]);
+    }
+
+    #[test]
+    fn test_spoofed_source_only_googlebot() {
+        let mut stats = Statistics::new();
+        for (ip, ua) in [
+            ("192.0.2.1", "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"),
+            ("192.0.2.2", "AdsBot-Google (+http://www.google.com/adsbot.html)"),
+            ("192.0.2.3", "Mozilla/5.0 (compatible; Google-InspectionTool/1.0)"),
+        ] {
+            let line = TestLine::new("example.com", "/", 200, ua).ip(ip).to_string();
+            stats.add_entry(&crate::parse_line(&line).unwrap());
+        }
+        assert_eq!(select_ips(&stats, &[BlocklistSource::Spoofed]), vec!["192.0.2.1"]);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let nets = build_blocklist(["192.0.2.1", "198.51.100.0/24", "2001:db8::1"], &options());
+        let opts = options();
+
+        let nginx = render_blocklist(&nets, BlocklistFormat::Nginx, &opts);
+        assert!(nginx.contains("deny 192.0.2.1;\n"));
+        assert!(nginx.contains("deny 198.51.100.0/24;\n"));
+
+        let apache = render_blocklist(&nets, BlocklistFormat::Apache, &opts);
+        assert!(apache.contains("    Require not ip 2001:db8::1\n"));
+        assert!(apache.ends_with("</RequireAll>\n"));
+
+        let iptables = render_blocklist(&nets, BlocklistFormat::Iptables, &opts);
+        assert!(iptables.contains("ip6tables -A INPUT -s 2001:db8::1 -j DROP"));
+
+        let nft = render_blocklist(&nets, BlocklistFormat::Nftables, &opts);
+        assert!(nft.contains("add element inet filter blocklist_v4 { 192.0.2.1, 198.51.100.0/24 }"));
+        assert!(nft.contains("add element inet filter blocklist_v6 { 2001:db8::1 }"));
+
+        let fail2ban = render_blocklist(&nets, BlocklistFormat::Fail2ban, &opts);
+        assert_eq!(fail2ban.lines().filter(|l| !l.starts_with('#')).count(), 3);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("NGINX".parse::<BlocklistFormat>(), Ok(BlocklistFormat::Nginx));
+        assert_eq!("nft".parse::<BlocklistFormat>(), Ok(BlocklistFormat::Nftables));
+        assert!("htaccess".parse::<BlocklistFormat>().is_err());
+    }
+}
@@ -131,6 +131,11 @@ static GOOGLE_IP_RANGES: Lazy<Vec<IpNetwork>> = Lazy::new(|| {
         .collect()
 });
 
+/// Google crawler IP ranges, e.g. for allowlisting Googlebot
+pub fn google_ip_ranges() -> &'static [IpNetwork] {
+    &GOOGLE_IP_RANGES
+}
+
 /// Detect if user agent belongs to a bot and identify it
 pub fn detect_bot(user_agent: &str) -> Option<(&'static str, &'static str)> {
     for sig in BOT_SIGNATURES {
@@ -155,6 +160,33 @@ pub fn is_google_ip(ips: &[String]) -> bool {
     false
 }
 
+/// Classify a Googlebot user agent into its crawler type
+/// (Smartphone, Desktop, Image, Video, News or Other)
+pub fn classify_googlebot(user_agent: &str) -> Option<&'static str> {
+    if !user_agent.contains("Googlebot") {
+        return None;
+    }
+
+    let crawler_type = if user_agent.contains("Googlebot-Image") {
+        "Image"
+    } else if user_agent.contains("Googlebot-Video") {
+        "Video"
+    } else if user_agent.contains("Googlebot-News") {
+        "News"
+    } else if user_agent.contains("Googlebot/") {
+        // The smartphone crawler emulates a Nexus 5X (Android + Mobile Safari)
+        if user_agent.contains("Android") && user_agent.contains("Mobile") {
+            "Smartphone"
+        } else {
+            "Desktop"
+        }
+    } else {
+        "Other"
+    };
+
+    Some(crawler_type)
+}
+
 /// Enrich a log entry with bot detection information
 pub fn enrich_entry(entry: &mut LogEntry) {
     if let Some((name, category)) = detect_bot(&entry.user_agent) {
@@ -166,6 +198,10 @@ pub fn enrich_entry(entry: &mut LogEntry) {
         if name.contains("Google") || name.contains("google") {
             entry.verified_googlebot = is_google_ip(&entry.ips);
         }
+
+        if let Some(crawler_type) = classify_googlebot(&entry.user_agent) {
+            entry.googlebot_type = crawler_type.to_string();
+        }
     }
 }
 
@@ -200,6 +236,18 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_classify_googlebot() {
+        let smartphone = "Mozilla/5.0 (Linux; Android 6.0.1; Nexus 5X Build/MMB29P) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.6099.71 Mobile Safari/537.36 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let desktop = "Mozilla/5.0 AppleWebKit/537.36 (KHTML, like Gecko; compatible; Googlebot/2.1; +http://www.google.com/bot.html) Chrome/120.0.6099.71 Safari/537.36";
+        assert_eq!(classify_googlebot(smartphone), Some("Smartphone"));
+        assert_eq!(classify_googlebot(desktop), Some("Desktop"));
+        assert_eq!(classify_googlebot("Googlebot-Image/1.0"), Some("Image"));
+        assert_eq!(classify_googlebot("Googlebot-Video/1.0"), Some("Video"));
+        assert_eq!(classify_googlebot("Googlebot-News"), Some("News"));
+        assert_eq!(classify_googlebot("bingbot/2.0"), None);
+    }
+
     #[test]
     fn test_google_ip_valid() {
         let ips = vec!["66.249.66.1".to_string()];
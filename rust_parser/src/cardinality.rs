@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Precision bounds for HyperLogLog sketches (16 bytes to 256 KB of registers)
+const MIN_PRECISION: u8 = 4;
+const MAX_PRECISION: u8 = 18;
+
+/// Stable 64-bit hash (FNV-1a followed by the murmur3 finalizer), so sketches
+/// built by different processes or versions can be merged
+fn hash64(value: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in value.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+/// HyperLogLog cardinality sketch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Create a sketch with 2^precision registers
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(MIN_PRECISION, MAX_PRECISION);
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Smallest standard error a sketch can deliver, at the maximum precision (~0.002)
+    pub fn min_error() -> f64 {
+        1.04 / ((1u64 << MAX_PRECISION) as f64).sqrt()
+    }
+
+    /// Create the smallest sketch whose standard error is at most `error` (e.g. 0.01).
+    /// Errors below `min_error` get the largest sketch.
+    pub fn with_error(error: f64) -> Self {
+        let registers = (1.04 / error).powi(2);
+        Self::new(registers.log2().ceil().min(MAX_PRECISION as f64) as u8)
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Standard error of the estimate
+    pub fn standard_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        let hash = hash64(value);
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rank = ((hash << self.precision) | (1 << (self.precision - 1))).leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Reduce to a lower precision so sketches of different sizes can be merged
+    fn fold(&self, precision: u8) -> HyperLogLog {
+        let shift = self.precision - precision;
+        let mut folded = HyperLogLog::new(precision);
+        for (index, &rank) in self.registers.iter().enumerate() {
+            if rank == 0 {
+                continue;
+            }
+            // The dropped index bits become the leading bits of the remaining hash
+            let dropped = index & ((1 << shift) - 1);
+            let new_rank = if dropped != 0 {
+                shift - (usize::BITS - dropped.leading_zeros()) as u8 + 1
+            } else {
+                shift + rank
+            };
+            let slot = &mut folded.registers[index >> shift];
+            *slot = (*slot).max(new_rank);
+        }
+        folded
+    }
+
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.precision < self.precision {
+            *self = self.fold(other.precision);
+        }
+        let other = if other.precision > self.precision {
+            other.fold(self.precision)
+        } else {
+            other.clone()
+        };
+        for (mine, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+
+    /// Estimated number of distinct values inserted
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        // Linear counting is more accurate for small cardinalities
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+/// Distinct value counter: an exact set, or a HyperLogLog sketch in approximate mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum UniqueCounter {
+    Exact(HashSet<String>),
+    Approx(HyperLogLog),
+}
+
+impl Default for UniqueCounter {
+    fn default() -> Self {
+        UniqueCounter::Exact(HashSet::new())
+    }
+}
+
+impl UniqueCounter {
+    /// Exact counter, or an approximate one with the given standard error
+    pub fn new(approx_error: Option<f64>) -> Self {
+        match approx_error {
+            Some(error) => UniqueCounter::Approx(HyperLogLog::with_error(error)),
+            None => UniqueCounter::default(),
+        }
+    }
+
+    pub fn insert(&mut self, value: impl AsRef<str>) {
+        let value = value.as_ref();
+        match self {
+            UniqueCounter::Exact(set) => {
+                if !set.contains(value) {
+                    set.insert(value.to_string());
+                }
+            }
+            UniqueCounter::Approx(hll) => hll.insert(value),
+        }
+    }
+
+    /// Number of distinct values (estimated in approximate mode)
+    pub fn len(&self) -> usize {
+        match self {
+            UniqueCounter::Exact(set) => set.len(),
+            UniqueCounter::Approx(hll) => hll.estimate().round() as usize,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, value: &str) -> Option<bool> {
+        match self {
+            UniqueCounter::Exact(set) => Some(set.contains(value)),
+            UniqueCounter::Approx(_) => None,
+        }
+    }
+
+    /// Standard error of `len()`, `None` for exact counters
+    pub fn standard_error(&self) -> Option<f64> {
+        match self {
+            UniqueCounter::Exact(_) => None,
+            UniqueCounter::Approx(hll) => Some(hll.standard_error()),
+        }
+    }
+
+    /// Merge another counter; mixing modes yields an approximate counter
+    pub fn merge(&mut self, other: &UniqueCounter) {
+        match (&mut *self, other) {
+            (UniqueCounter::Exact(mine), UniqueCounter::Exact(theirs)) => {
+                for value in theirs {
+                    if !mine.contains(value) {
+                        mine.insert(value.clone());
+                    }
+                }
+            }
+            (UniqueCounter::Approx(mine), UniqueCounter::Approx(theirs)) => mine.merge(theirs),
+            (UniqueCounter::Approx(mine), UniqueCounter::Exact(theirs)) => {
+                for value in theirs {
+                    mine.insert(value);
+                }
+            }
+            (UniqueCounter::Exact(mine), UniqueCounter::Approx(theirs)) => {
+                let mut hll = theirs.clone();
+                for value in mine.iter() {
+                    hll.insert(value);
+                }
+                *self = UniqueCounter::Approx(hll);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_within_error() {
+        let mut hll = HyperLogLog::with_error(0.01);
+        assert_eq!(hll.precision(), 14);
+        let largest = HyperLogLog::with_error(HyperLogLog::min_error());
+        assert_eq!(largest.standard_error(), HyperLogLog::min_error());
+        for i in 0..100_000 {
+            hll.insert(&format!("/product/{}", i));
+        }
+        let estimate = hll.estimate();
+        assert!((estimate - 100_000.0).abs() / 100_000.0 < 0.03, "estimate {}", estimate);
+    }
+
+    #[test]
+    fn test_merge_and_fold() {
+        let mut left = HyperLogLog::new(12);
+        let mut right = HyperLogLog::new(10);
+        let mut whole = HyperLogLog::new(10);
+        for i in 0..20_000 {
+            let value = format!("10.0.{}.{}", i / 256, i % 256);
+            if i % 2 == 0 {
+                left.insert(&value);
+            } else {
+                right.insert(&value);
+            }
+            whole.insert(&value);
+        }
+
+        left.merge(&right);
+        assert_eq!(left.precision(), 10);
+        assert_eq!(left, whole);
+    }
+
+    #[test]
+    fn test_unique_counter_modes() {
+        let mut exact = UniqueCounter::default();
+        exact.insert("/a");
+        exact.insert(String::from("/a"));
+        exact.insert("/b");
+        assert_eq!(exact.len(), 2);
+        assert_eq!(exact.standard_error(), None);
+
+        let mut approx = UniqueCounter::new(Some(0.02));
+        approx.insert("/b");
+        approx.insert("/c");
+        exact.merge(&approx);
+        assert!(matches!(exact, UniqueCounter::Approx(_)));
+        assert_eq!(exact.len(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut counter = UniqueCounter::new(Some(0.05));
+        counter.insert("/a");
+        let json = serde_json::to_string(&counter).unwrap();
+        let restored: UniqueCounter = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.len(), 1);
+    }
+}
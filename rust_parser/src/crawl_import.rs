@@ -0,0 +1,408 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "cli")]
+use std::fs::File;
+#[cfg(feature = "cli")]
+use std::io;
+#[cfg(feature = "cli")]
+use std::path::Path;
+
+use crate::aggregator::{Statistics, TrafficSplit, UrlStats};
+#[cfg(feature = "cli")]
+use crate::normalizer::UrlNormalizer;
+use crate::resource_type;
+use crate::sitemap::normalize_host;
+#[cfg(feature = "cli")]
+use crate::sitemap::split_loc;
+
+/// Header names tried for each attribute, compared case-insensitively
+/// (Screaming Frog and Sitebulb exports)
+#[cfg(feature = "cli")]
+const URL_COLUMNS: &[&str] = &["Address", "URL"];
+#[cfg(feature = "cli")]
+const INDEXABLE_COLUMNS: &[&str] = &["Indexability", "Indexable"];
+#[cfg(feature = "cli")]
+const CANONICAL_COLUMNS: &[&str] = &["Canonical Link Element 1", "Canonical URL", "Canonical"];
+#[cfg(feature = "cli")]
+const DEPTH_COLUMNS: &[&str] = &["Crawl Depth", "Depth"];
+#[cfg(feature = "cli")]
+const INLINKS_COLUMNS: &[&str] = &["Unique Inlinks", "Inlinks", "No. Unique Inlinks"];
+#[cfg(feature = "cli")]
+const TITLE_COLUMNS: &[&str] = &["Title 1", "Title"];
+
+/// Crawl attributes of one URL; empty strings and `None` mean not exported
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CrawlRecord {
+    pub indexable: Option<bool>,
+    pub canonical: String,
+    pub depth: Option<u32>,
+    pub inlinks: Option<u64>,
+    pub title: String,
+}
+
+/// Site crawl export keyed by host and, like the aggregated URLs, path and query
+#[derive(Debug, Clone, Default)]
+pub struct CrawlData {
+    /// Host without `www.` (empty for relative URLs) -> URL key -> record
+    pub records: HashMap<String, HashMap<String, CrawlRecord>>,
+}
+
+#[cfg(feature = "cli")]
+fn parse_indexable(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "indexable" | "yes" | "true" | "1" => Some(true),
+        "non-indexable" | "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a number, ignoring thousands separators
+#[cfg(feature = "cli")]
+fn parse_count<T: std::str::FromStr>(value: &str) -> Option<T> {
+    value.trim().replace(',', "").parse().ok()
+}
+
+impl CrawlData {
+    /// Number of crawled URLs
+    pub fn len(&self) -> usize {
+        self.records.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Record of a URL key on `host`; relative crawl URLs match any host
+    pub fn get(&self, host: &str, url: &str) -> Option<&CrawlRecord> {
+        self.records
+            .get(host)
+            .and_then(|urls| urls.get(url))
+            .or_else(|| self.records.get("").and_then(|urls| urls.get(url)))
+    }
+
+    /// Whether the crawl has URLs on `host`
+    fn covers(&self, host: &str) -> bool {
+        self.records.contains_key(host) || self.records.contains_key("")
+    }
+
+    /// Read a crawl CSV export. The URL column is `url_column` when given, otherwise
+    /// `Address` or `URL`; attribute columns are optional and found by header name.
+    #[cfg(feature = "cli")]
+    pub fn from_reader<R: io::Read>(
+        reader: R,
+        url_column: Option<&str>,
+        normalizer: Option<&UrlNormalizer>,
+    ) -> io::Result<Self> {
+        let mut csv = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = csv.headers().map_err(io::Error::other)?.clone();
+        let find = |names: &[&str]| {
+            headers
+                .iter()
+                .position(|h| names.iter().any(|name| h.trim().eq_ignore_ascii_case(name)))
+        };
+
+        let url_names = url_column.map_or(URL_COLUMNS.to_vec(), |name| vec![name]);
+        let url_index = find(&url_names).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line 1: missing URL column {:?}", url_names.join(" or ")),
+            )
+        })?;
+        let indexable = find(INDEXABLE_COLUMNS);
+        let canonical = find(CANONICAL_COLUMNS);
+        let depth = find(DEPTH_COLUMNS);
+        let inlinks = find(INLINKS_COLUMNS);
+        let title = find(TITLE_COLUMNS);
+
+        let mut records: HashMap<String, HashMap<String, CrawlRecord>> = HashMap::new();
+        for (row, result) in csv.records().enumerate() {
+            let record = result.map_err(|e| {
+                let line = e.position().map_or(row as u64 + 2, |p| p.line());
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, e))
+            })?;
+            let field = |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or("");
+
+            let url = field(Some(url_index)).trim();
+            if url.is_empty() {
+                continue;
+            }
+            let (host, path) = split_loc(url);
+            let key = match normalizer {
+                Some(normalizer) => normalizer.normalize(path),
+                None => path.to_string(),
+            };
+            // Keep the first row when several crawled URLs map to one key
+            records.entry(host).or_default().entry(key).or_insert_with(|| CrawlRecord {
+                indexable: parse_indexable(field(indexable)),
+                canonical: field(canonical).trim().to_string(),
+                depth: parse_count(field(depth)),
+                inlinks: parse_count(field(inlinks)),
+                title: field(title).trim().to_string(),
+            });
+        }
+
+        Ok(CrawlData { records })
+    }
+
+    /// Load a crawl CSV export from a file
+    #[cfg(feature = "cli")]
+    pub fn from_file(
+        path: &Path,
+        url_column: Option<&str>,
+        normalizer: Option<&UrlNormalizer>,
+    ) -> io::Result<Self> {
+        Self::from_reader(File::open(path)?, url_column, normalizer)
+    }
+}
+
+/// A URL with its log traffic and crawl attributes (`None` when not in the crawl)
+#[derive(Debug, Clone, Serialize)]
+pub struct CrawlUrlRow {
+    /// Host without `www.`, empty for relative crawl URLs
+    pub host: String,
+    pub url: String,
+    pub hits: u64,
+    pub bot_hits: u64,
+    pub human_hits: u64,
+    pub crawl: Option<CrawlRecord>,
+}
+
+/// Crawled URLs and bot hits at one crawl depth
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DepthStats {
+    pub urls: usize,
+    pub bot_crawled_urls: usize,
+    pub bot_hits: u64,
+}
+
+/// Left join of the per-URL log aggregation with a crawl export
+#[derive(Debug, Clone, Serialize)]
+pub struct CrawlReport {
+    pub crawl_urls: usize,
+    /// Log URLs found in the crawl
+    pub joined_urls: usize,
+    /// Pages (HTML resources) with traffic in the logs that the crawl did not find
+    pub orphan_urls_count: usize,
+    /// Crawled URLs no bot requested
+    pub uncrawled_urls_count: usize,
+    /// Bot hits on URLs the crawl marks as non-indexable
+    pub non_indexable_bot_hits: u64,
+    /// Share of all bot requests spent on non-indexable URLs, in percent
+    pub non_indexable_bot_pct: f64,
+    pub by_depth: BTreeMap<u32, DepthStats>,
+    /// Log URLs with the most bot hits and their crawl attributes
+    pub top_urls: Vec<CrawlUrlRow>,
+    pub orphan_urls: Vec<CrawlUrlRow>,
+    /// Shallowest and best linked first
+    pub uncrawled_urls: Vec<CrawlUrlRow>,
+    pub non_indexable_urls: Vec<CrawlUrlRow>,
+}
+
+fn by_bot_hits(a: &CrawlUrlRow, b: &CrawlUrlRow) -> std::cmp::Ordering {
+    b.bot_hits
+        .cmp(&a.bot_hits)
+        .then_with(|| b.hits.cmp(&a.hits))
+        .then_with(|| a.url.cmp(&b.url))
+        .then_with(|| a.host.cmp(&b.host))
+}
+
+/// Traffic of a URL on one host; an empty host takes every domain
+fn traffic_on(url_stats: &UrlStats, host: &str) -> TrafficSplit {
+    let mut traffic = TrafficSplit::default();
+    for (domain, domain_traffic) in &url_stats.by_domain {
+        if host.is_empty() || normalize_host(domain) == host {
+            traffic.merge(domain_traffic);
+        }
+    }
+    traffic
+}
+
+/// Join crawl attributes onto the per-URL stats, matching on host and URL. Log
+/// traffic on hosts the crawl has no URLs for is left out.
+pub fn crawl_report(crawl: &CrawlData, stats: &Statistics, top_n: usize) -> CrawlReport {
+    let row = |host: &str, url: &str, traffic: TrafficSplit| CrawlUrlRow {
+        host: host.to_string(),
+        url: url.to_string(),
+        hits: traffic.hits,
+        bot_hits: traffic.bot_hits,
+        human_hits: traffic.human_hits,
+        crawl: crawl.get(host, url).cloned(),
+    };
+
+    let mut top_urls = Vec::new();
+    let mut orphan_urls = Vec::new();
+    let mut non_indexable_urls = Vec::new();
+    let mut joined_urls = 0;
+    for (url, url_stats) in &stats.hits_by_url {
+        let mut by_host: BTreeMap<String, TrafficSplit> = BTreeMap::new();
+        for (domain, traffic) in &url_stats.by_domain {
+            by_host.entry(normalize_host(domain)).or_default().merge(traffic);
+        }
+        for (host, traffic) in by_host {
+            if !crawl.covers(&host) {
+                continue;
+            }
+            let row = row(&host, url, traffic);
+            match &row.crawl {
+                Some(record) => {
+                    joined_urls += 1;
+                    if record.indexable == Some(false) && row.bot_hits > 0 {
+                        non_indexable_urls.push(row.clone());
+                    }
+                }
+                None if resource_type::from_url(url) == "html" => orphan_urls.push(row.clone()),
+                None => {}
+            }
+            top_urls.push(row);
+        }
+    }
+
+    let mut by_depth: BTreeMap<u32, DepthStats> = BTreeMap::new();
+    let mut uncrawled_urls = Vec::new();
+    for (host, urls) in &crawl.records {
+        for (url, record) in urls {
+            let traffic = stats
+                .hits_by_url
+                .get(url)
+                .map(|url_stats| traffic_on(url_stats, host))
+                .unwrap_or_default();
+            if let Some(depth) = record.depth {
+                let depth_stats = by_depth.entry(depth).or_default();
+                depth_stats.urls += 1;
+                depth_stats.bot_hits += traffic.bot_hits;
+                if traffic.bot_hits > 0 {
+                    depth_stats.bot_crawled_urls += 1;
+                }
+            }
+            if traffic.bot_hits == 0 {
+                uncrawled_urls.push(row(host, url, traffic));
+            }
+        }
+    }
+
+    let non_indexable_bot_hits: u64 = non_indexable_urls.iter().map(|r| r.bot_hits).sum();
+    let non_indexable_bot_pct = if stats.bot_requests > 0 {
+        non_indexable_bot_hits as f64 / stats.bot_requests as f64 * 100.0
+    } else {
+        0.0
+    };
+    let (orphan_urls_count, uncrawled_urls_count) = (orphan_urls.len(), uncrawled_urls.len());
+
+    top_urls.sort_by(by_bot_hits);
+    orphan_urls.sort_by(by_bot_hits);
+    non_indexable_urls.sort_by(by_bot_hits);
+    uncrawled_urls.sort_by(|a, b| {
+        let (a_crawl, b_crawl) = (a.crawl.as_ref(), b.crawl.as_ref());
+        let depth = |c: Option<&CrawlRecord>| c.and_then(|c| c.depth).unwrap_or(u32::MAX);
+        let inlinks = |c: Option<&CrawlRecord>| c.and_then(|c| c.inlinks).unwrap_or(0);
+        depth(a_crawl)
+            .cmp(&depth(b_crawl))
+            .then_with(|| inlinks(b_crawl).cmp(&inlinks(a_crawl)))
+            .then_with(|| a.url.cmp(&b.url))
+            .then_with(|| a.host.cmp(&b.host))
+    });
+    for list in [
+        &mut top_urls,
+        &mut orphan_urls,
+        &mut uncrawled_urls,
+        &mut non_indexable_urls,
+    ] {
+        list.truncate(top_n);
+    }
+
+    CrawlReport {
+        crawl_urls: crawl.len(),
+        joined_urls,
+        orphan_urls_count,
+        uncrawled_urls_count,
+        non_indexable_bot_hits,
+        non_indexable_bot_pct,
+        by_depth,
+        top_urls,
+        orphan_urls,
+        uncrawled_urls,
+        non_indexable_urls,
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use crate::test_util::test_line;
+
+    const SCREAMING_FROG: &str = "\
+\"Address\",\"Content Type\",\"Status Code\",\"Indexability\",\"Title 1\",\"Canonical Link Element 1\",\"Crawl Depth\",\"Unique Inlinks\"
+\"https://example.com/\",\"text/html\",\"200\",\"Indexable\",\"Home\",\"https://example.com/\",\"0\",\"1,204\"
+\"https://example.com/shoes?sort=price\",\"text/html\",\"200\",\"Non-Indexable\",\"Shoes, sorted\",\"https://example.com/shoes\",\"2\",\"3\"
+\"https://example.com/shoes\",\"text/html\",\"200\",\"Indexable\",\"Shoes\",\"\",\"1\",\"40\"
+\"https://example.com/deep/page\",\"text/html\",\"200\",\"Indexable\",\"Deep\",\"\",\"5\",\"\"
+";
+
+    #[test]
+    fn test_parse_screaming_frog_export() {
+        let crawl = CrawlData::from_reader(SCREAMING_FROG.as_bytes(), None, None).unwrap();
+        assert_eq!(crawl.len(), 4);
+        let records = &crawl.records["example.com"];
+        let home = &records["/"];
+        assert_eq!(home.indexable, Some(true));
+        assert_eq!(home.inlinks, Some(1204));
+        assert_eq!(home.depth, Some(0));
+        let sorted = &records["/shoes?sort=price"];
+        assert_eq!(sorted.indexable, Some(false));
+        assert_eq!(sorted.title, "Shoes, sorted");
+        assert_eq!(sorted.canonical, "https://example.com/shoes");
+        assert_eq!(records["/deep/page"].inlinks, None);
+
+        let err =
+            CrawlData::from_reader(SCREAMING_FROG.as_bytes(), Some("Page"), None).unwrap_err();
+        assert!(err.to_string().contains("missing URL column \"Page\""));
+    }
+
+    #[test]
+    fn test_crawl_report() {
+        let pipeline = Pipeline::new();
+        let mut stats = pipeline.new_statistics();
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        for (url, ua) in [
+            ("/", googlebot),
+            ("/shoes?sort=price", googlebot),
+            ("/shoes?sort=price", googlebot),
+            ("/old-promo", googlebot),
+            ("/old-promo", "Mozilla/5.0 Chrome/120"),
+            ("/app.js", googlebot),
+            ("/deep/page", googlebot),
+            ("/blog-only", googlebot),
+        ] {
+            // The blog subdomain is not part of the crawl
+            let domain = if url.starts_with("/deep") || url.starts_with("/blog") {
+                "blog.example.com"
+            } else {
+                "example.com"
+            };
+            let line = test_line(domain, url, 200, ua);
+            stats.add_entry(&pipeline.process_line(&line).unwrap());
+        }
+        let crawl = CrawlData::from_reader(SCREAMING_FROG.as_bytes(), None, None).unwrap();
+        let report = crawl_report(&crawl, &stats, 10);
+
+        assert_eq!(report.joined_urls, 2);
+        assert_eq!(report.orphan_urls_count, 1);
+        assert_eq!(report.orphan_urls[0].url, "/old-promo");
+        assert_eq!(report.orphan_urls[0].human_hits, 1);
+
+        let uncrawled: Vec<&str> = report
+            .uncrawled_urls
+            .iter()
+            .map(|r| r.url.as_str())
+            .collect();
+        assert_eq!(uncrawled, vec!["/shoes", "/deep/page"]);
+
+        assert_eq!(report.non_indexable_bot_hits, 2);
+        assert_eq!(report.non_indexable_urls[0].url, "/shoes?sort=price");
+        assert!((report.non_indexable_bot_pct - 2.0 / 7.0 * 100.0).abs() < 1e-9);
+        assert_eq!(report.by_depth[&2].bot_crawled_urls, 1);
+        assert_eq!(report.by_depth[&5].bot_hits, 0);
+        assert_eq!(report.top_urls[0].url, "/shoes?sort=price");
+    }
+}
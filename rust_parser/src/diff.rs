@@ -0,0 +1,409 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::aggregator::{Statistics, UrlStats};
+use crate::path_tree::build_path_tree;
+
+/// Change of a counter between the two periods
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Delta {
+    pub before: u64,
+    pub after: u64,
+    pub change: i64,
+    /// Relative change in percent, `None` when the counter was zero before
+    pub change_pct: Option<f64>,
+}
+
+impl Delta {
+    pub fn new(before: u64, after: u64) -> Self {
+        Delta {
+            before,
+            after,
+            change: after as i64 - before as i64,
+            change_pct: (before > 0)
+                .then(|| (after as f64 - before as f64) / before as f64 * 100.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TotalsDiff {
+    pub total_requests: Delta,
+    pub total_bytes: Delta,
+    pub unique_urls: Delta,
+    pub bot_requests: Delta,
+    pub human_requests: Delta,
+    pub verified_googlebot_requests: Delta,
+}
+
+/// Traffic change for one directory of the path tree
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionDelta {
+    pub path: String,
+    pub hits: Delta,
+    pub bot_hits: Delta,
+    pub googlebot_hits: Delta,
+}
+
+/// A URL seen in only one of the periods, or with a new 404
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlHits {
+    pub url: String,
+    pub hits: u64,
+    pub bot_hits: u64,
+}
+
+/// A URL whose most frequent status code changed between the periods
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusChange {
+    pub url: String,
+    pub before: u16,
+    pub after: u16,
+    pub hits_after: u64,
+}
+
+/// Period-over-period comparison of two `Statistics`
+#[derive(Debug, Clone, Serialize)]
+pub struct StatisticsDiff {
+    pub totals: TotalsDiff,
+    pub by_status: BTreeMap<u16, Delta>,
+    /// Bots with the largest absolute change in hits
+    pub by_bot: Vec<(String, Delta)>,
+    /// URLs with the largest absolute change in hits
+    pub by_url: Vec<(String, Delta)>,
+    pub by_section: Vec<SectionDelta>,
+    pub by_segment: Vec<(String, Delta)>,
+    pub new_urls_count: usize,
+    pub new_urls: Vec<UrlHits>,
+    pub disappeared_urls_count: usize,
+    pub disappeared_urls: Vec<UrlHits>,
+    /// URLs answering 404 after but not before; hits are the 404 responses and bot
+    /// hits the 404 responses to bots
+    pub new_404s: Vec<UrlHits>,
+    /// URLs crawled by bots before but not after, with their hits before
+    pub stopped_crawling: Vec<UrlHits>,
+    pub status_changes_count: usize,
+    pub status_changes: Vec<StatusChange>,
+}
+
+/// Keep the `top_n` entries with the largest absolute change
+fn top_changes(
+    deltas: impl Iterator<Item = (String, Delta)>,
+    top_n: usize,
+) -> Vec<(String, Delta)> {
+    let mut deltas: Vec<(String, Delta)> = deltas.filter(|(_, d)| d.change != 0).collect();
+    deltas.sort_by(|a, b| {
+        b.1.change
+            .unsigned_abs()
+            .cmp(&a.1.change.unsigned_abs())
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    deltas.truncate(top_n);
+    deltas
+}
+
+/// Deltas for every key present in either map
+fn map_deltas<'a, K: Ord + Clone + 'a>(
+    before: impl Iterator<Item = (&'a K, u64)>,
+    after: impl Iterator<Item = (&'a K, u64)>,
+) -> BTreeMap<K, Delta> {
+    let mut counts: BTreeMap<K, (u64, u64)> = BTreeMap::new();
+    for (key, count) in before {
+        counts.entry(key.clone()).or_default().0 += count;
+    }
+    for (key, count) in after {
+        counts.entry(key.clone()).or_default().1 += count;
+    }
+    counts
+        .into_iter()
+        .map(|(key, (before, after))| (key, Delta::new(before, after)))
+        .collect()
+}
+
+/// Most frequent status code of a URL, the lowest code on ties
+fn dominant_status(stats: &UrlStats) -> Option<u16> {
+    stats
+        .status_codes
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(code, _)| *code)
+}
+
+/// Hits, bot hits and Googlebot hits per path tree section
+fn section_hits(stats: &Statistics) -> HashMap<String, (u64, u64, u64)> {
+    let depth = stats.config.path_depth;
+    let googlebot_by_url: HashMap<String, UrlStats> = stats
+        .hits_by_url
+        .iter()
+        .map(|(url, url_stats)| {
+            let hits = url_stats.googlebot_types.values().sum();
+            (
+                url.clone(),
+                UrlStats {
+                    hits,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+    let googlebot: HashMap<String, u64> = build_path_tree(&googlebot_by_url, depth)
+        .sections()
+        .into_iter()
+        .map(|row| (row.path, row.hits))
+        .collect();
+
+    build_path_tree(&stats.hits_by_url, depth)
+        .sections()
+        .into_iter()
+        .map(|row| {
+            let googlebot_hits = googlebot.get(&row.path).copied().unwrap_or(0);
+            (row.path, (row.hits, row.bot_hits, googlebot_hits))
+        })
+        .collect()
+}
+
+/// URL -> 404 responses to bots, from the bot x URL crawl matrix
+fn bot_not_found(stats: &Statistics) -> HashMap<&str, u64> {
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    for urls in stats.hits_by_bot_url.values() {
+        for (url, bot_url) in urls {
+            if let Some(count) = bot_url.status_codes.get(&404) {
+                *counts.entry(url.as_str()).or_insert(0) += count;
+            }
+        }
+    }
+    counts
+}
+
+fn sorted_url_hits(mut urls: Vec<UrlHits>, top_n: usize) -> (usize, Vec<UrlHits>) {
+    let count = urls.len();
+    urls.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.url.cmp(&b.url)));
+    urls.truncate(top_n);
+    (count, urls)
+}
+
+/// Compare two periods, keeping the `top_n` items of every list
+pub fn compare(before: &Statistics, after: &Statistics, top_n: usize) -> StatisticsDiff {
+    let totals = TotalsDiff {
+        total_requests: Delta::new(before.total_requests, after.total_requests),
+        total_bytes: Delta::new(before.total_bytes, after.total_bytes),
+        unique_urls: Delta::new(
+            before.unique_urls.len() as u64,
+            after.unique_urls.len() as u64,
+        ),
+        bot_requests: Delta::new(before.bot_requests, after.bot_requests),
+        human_requests: Delta::new(before.human_requests, after.human_requests),
+        verified_googlebot_requests: Delta::new(
+            before.verified_googlebot_requests,
+            after.verified_googlebot_requests,
+        ),
+    };
+
+    let by_status = map_deltas(
+        before.status_distribution.iter().map(|(k, v)| (k, *v)),
+        after.status_distribution.iter().map(|(k, v)| (k, *v)),
+    );
+    let by_bot = map_deltas(
+        before.hits_by_bot.iter().map(|(k, v)| (k, v.hits)),
+        after.hits_by_bot.iter().map(|(k, v)| (k, v.hits)),
+    );
+    let by_url = map_deltas(
+        before.hits_by_url.iter().map(|(k, v)| (k, v.hits)),
+        after.hits_by_url.iter().map(|(k, v)| (k, v.hits)),
+    );
+    let by_segment = map_deltas(
+        before.hits_by_segment.iter().map(|(k, v)| (k, v.hits)),
+        after.hits_by_segment.iter().map(|(k, v)| (k, v.hits)),
+    );
+
+    let sections_before = section_hits(before);
+    let sections_after = section_hits(after);
+    let paths: BTreeSet<&String> = sections_before
+        .keys()
+        .chain(sections_after.keys())
+        .collect();
+    let mut by_section: Vec<SectionDelta> = paths
+        .into_iter()
+        .map(|path| {
+            let b = sections_before.get(path).copied().unwrap_or_default();
+            let a = sections_after.get(path).copied().unwrap_or_default();
+            SectionDelta {
+                path: path.clone(),
+                hits: Delta::new(b.0, a.0),
+                bot_hits: Delta::new(b.1, a.1),
+                googlebot_hits: Delta::new(b.2, a.2),
+            }
+        })
+        .collect();
+    by_section.sort_by(|a, b| {
+        (b.hits.before + b.hits.after)
+            .cmp(&(a.hits.before + a.hits.after))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    by_section.truncate(top_n);
+
+    let url_hits = |url: &String, stats: &UrlStats| UrlHits {
+        url: url.clone(),
+        hits: stats.hits,
+        bot_hits: stats.bot_hits,
+    };
+    let bot_not_found_after = bot_not_found(after);
+    let mut new_urls = Vec::new();
+    let mut new_404s = Vec::new();
+    let mut status_changes = Vec::new();
+    for (url, stats) in &after.hits_by_url {
+        let previous = before.hits_by_url.get(url);
+        match previous {
+            None => new_urls.push(url_hits(url, stats)),
+            Some(previous) => {
+                if let (Some(old), Some(new)) = (dominant_status(previous), dominant_status(stats))
+                {
+                    if old != new {
+                        status_changes.push(StatusChange {
+                            url: url.clone(),
+                            before: old,
+                            after: new,
+                            hits_after: stats.hits,
+                        });
+                    }
+                }
+            }
+        }
+        let not_found = stats.status_codes.get(&404).copied().unwrap_or(0);
+        if not_found > 0 && previous.is_none_or(|p| !p.status_codes.contains_key(&404)) {
+            new_404s.push(UrlHits {
+                url: url.clone(),
+                hits: not_found,
+                bot_hits: bot_not_found_after.get(url.as_str()).copied().unwrap_or(0),
+            });
+        }
+    }
+
+    let mut disappeared_urls = Vec::new();
+    let mut stopped_crawling = Vec::new();
+    for (url, stats) in &before.hits_by_url {
+        let current = after.hits_by_url.get(url);
+        if current.is_none() {
+            disappeared_urls.push(url_hits(url, stats));
+        }
+        if stats.bot_hits > 0 && current.is_none_or(|c| c.bot_hits == 0) {
+            stopped_crawling.push(url_hits(url, stats));
+        }
+    }
+
+    let (new_urls_count, new_urls) = sorted_url_hits(new_urls, top_n);
+    let (disappeared_urls_count, disappeared_urls) = sorted_url_hits(disappeared_urls, top_n);
+    let (_, new_404s) = sorted_url_hits(new_404s, top_n);
+    let (_, stopped_crawling) = sorted_url_hits(stopped_crawling, top_n);
+
+    let status_changes_count = status_changes.len();
+    status_changes.sort_by(|a, b| {
+        b.hits_after
+            .cmp(&a.hits_after)
+            .then_with(|| a.url.cmp(&b.url))
+    });
+    status_changes.truncate(top_n);
+
+    StatisticsDiff {
+        totals,
+        by_status,
+        by_bot: top_changes(by_bot.into_iter(), top_n),
+        by_url: top_changes(by_url.into_iter(), top_n),
+        by_section,
+        by_segment: top_changes(by_segment.into_iter(), top_n),
+        new_urls_count,
+        new_urls,
+        disappeared_urls_count,
+        disappeared_urls,
+        new_404s,
+        stopped_crawling,
+        status_changes_count,
+        status_changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use crate::test_util::test_line;
+
+    fn stats_from(lines: &[(&str, u16, &str)]) -> Statistics {
+        let pipeline = Pipeline::new();
+        let mut stats = pipeline.new_statistics();
+        for (url, status, ua) in lines {
+            let line = test_line("example.com", url, *status, ua);
+            stats.add_entry(&pipeline.process_line(&line).unwrap());
+        }
+        stats
+    }
+
+    const GOOGLEBOT: &str =
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+    const BROWSER: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/120.0.0.0";
+
+    #[test]
+    fn test_compare_periods() {
+        let before = stats_from(&[
+            ("/old/page", 200, GOOGLEBOT),
+            ("/old/page", 200, GOOGLEBOT),
+            ("/old/page", 200, BROWSER),
+            ("/shop/item", 200, GOOGLEBOT),
+            ("/shop/item", 200, BROWSER),
+            ("/about", 200, BROWSER),
+        ]);
+        let after = stats_from(&[
+            ("/old/page", 404, BROWSER),
+            ("/shop/item", 301, GOOGLEBOT),
+            ("/shop/item", 404, BROWSER),
+            ("/new/page", 200, GOOGLEBOT),
+            ("/new/page", 200, GOOGLEBOT),
+            ("/new/page", 200, GOOGLEBOT),
+        ]);
+        let diff = compare(&before, &after, 10);
+
+        assert_eq!(diff.totals.total_requests, Delta::new(6, 6));
+        assert_eq!(diff.totals.bot_requests.change, 1);
+        assert_eq!(diff.by_status[&404], Delta::new(0, 2));
+        assert_eq!(diff.by_status[&200].change, -3);
+        assert_eq!(diff.by_status[&200].change_pct, Some(-50.0));
+
+        assert_eq!(diff.new_urls_count, 1);
+        assert_eq!(diff.new_urls[0].url, "/new/page");
+        assert_eq!(diff.disappeared_urls[0].url, "/about");
+        assert_eq!(diff.new_404s[0].url, "/old/page");
+        assert_eq!(diff.new_404s[1].url, "/shop/item");
+        assert_eq!((diff.new_404s[1].hits, diff.new_404s[1].bot_hits), (1, 0));
+        assert_eq!(diff.stopped_crawling.len(), 1);
+        assert_eq!(diff.stopped_crawling[0].url, "/old/page");
+        assert_eq!(diff.stopped_crawling[0].hits, 3);
+        assert_eq!(diff.stopped_crawling[0].bot_hits, 2);
+
+        let urls: Vec<&str> = diff.status_changes.iter().map(|c| c.url.as_str()).collect();
+        assert_eq!(urls, vec!["/shop/item", "/old/page"]);
+        assert_eq!(
+            (diff.status_changes[0].before, diff.status_changes[0].after),
+            (200, 301)
+        );
+
+        assert_eq!(diff.by_url[0].0, "/new/page");
+        assert_eq!(diff.by_url[0].1.change_pct, None);
+    }
+
+    #[test]
+    fn test_googlebot_hits_by_section() {
+        let before = stats_from(&[
+            ("/old/a", 200, GOOGLEBOT),
+            ("/old/b", 200, GOOGLEBOT),
+            ("/old/b", 200, BROWSER),
+        ]);
+        let after = stats_from(&[("/new/a", 200, GOOGLEBOT), ("/old/a", 301, BROWSER)]);
+        let diff = compare(&before, &after, 10);
+
+        let section = |path: &str| diff.by_section.iter().find(|s| s.path == path).unwrap();
+        assert_eq!(section("/").googlebot_hits, Delta::new(2, 1));
+        assert_eq!(section("/old/").googlebot_hits, Delta::new(2, 0));
+        assert_eq!(section("/old/").hits, Delta::new(3, 1));
+        assert_eq!(section("/new/").googlebot_hits, Delta::new(0, 1));
+    }
+}
@@ -0,0 +1,607 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::parser::LogEntry;
+
+/// Marker that precedes the metadata section at the end of every MaxMind DB file
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+/// Size of the zeroed separator between the search tree and the data section
+const DATA_SEPARATOR_SIZE: usize = 16;
+
+/// Maximum nesting of maps, arrays and pointers, so a pointer loop in a corrupt
+/// database fails instead of overflowing the stack
+const MAX_DECODE_DEPTH: usize = 64;
+
+/// A decoded value from the MaxMind DB data section
+#[derive(Debug, Clone, PartialEq)]
+pub enum MmdbValue {
+    String(String),
+    Double(f64),
+    Bytes(Vec<u8>),
+    Uint(u64),
+    Uint128(u128),
+    Int(i32),
+    Map(BTreeMap<String, MmdbValue>),
+    Array(Vec<MmdbValue>),
+    Bool(bool),
+    Float(f32),
+}
+
+impl MmdbValue {
+    /// Follow a path of map keys (e.g. `["country", "iso_code"]`)
+    pub fn get(&self, path: &[&str]) -> Option<&MmdbValue> {
+        let mut value = self;
+        for key in path {
+            match value {
+                MmdbValue::Map(map) => value = map.get(*key)?,
+                _ => return None,
+            }
+        }
+        Some(value)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MmdbValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            MmdbValue::Uint(n) => Some(*n),
+            MmdbValue::Uint128(n) => u64::try_from(*n).ok(),
+            MmdbValue::Int(n) => u64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid MaxMind DB: {}", msg))
+}
+
+/// Reader for MaxMind DB (`.mmdb`) files such as GeoLite2-Country and GeoLite2-ASN
+#[derive(Debug)]
+pub struct MmdbReader {
+    data: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u64,
+    data_start: usize,
+    ipv4_start: usize,
+}
+
+impl MmdbReader {
+    /// Load a database file fully into memory
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    /// Build a reader from the raw database bytes
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self> {
+        let marker_pos = data
+            .windows(METADATA_MARKER.len())
+            .rposition(|w| w == METADATA_MARKER)
+            .ok_or_else(|| invalid_data("metadata marker not found"))?;
+        let metadata_start = marker_pos + METADATA_MARKER.len();
+
+        let decoder = Decoder { data: &data[metadata_start..] };
+        let (metadata, _) = decoder.decode(0)?;
+
+        let field = |name: &str| {
+            metadata
+                .get(&[name])
+                .and_then(MmdbValue::as_u64)
+                .ok_or_else(|| invalid_data(&format!("missing metadata field {}", name)))
+        };
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")?;
+
+        if !matches!(record_size, 24 | 28 | 32) {
+            return Err(invalid_data("unsupported record size"));
+        }
+
+        let data_start = node_count
+            .checked_mul(record_size * 2 / 8)
+            .and_then(|tree_size| tree_size.checked_add(DATA_SEPARATOR_SIZE))
+            .ok_or_else(|| invalid_data("search tree exceeds file size"))?;
+        if data_start > marker_pos {
+            return Err(invalid_data("search tree exceeds file size"));
+        }
+
+        let mut reader = MmdbReader {
+            data,
+            node_count,
+            record_size,
+            ip_version,
+            data_start,
+            ipv4_start: 0,
+        };
+
+        // IPv4 addresses live under ::/96 in IPv6 databases
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = reader.read_record(node, 0)?;
+            }
+            reader.ipv4_start = node;
+        }
+
+        Ok(reader)
+    }
+
+    fn read_record(&self, node: usize, bit: u8) -> io::Result<usize> {
+        let node_bytes = self.record_size * 2 / 8;
+        let offset = node * node_bytes;
+        let b = self
+            .data
+            .get(offset..offset + node_bytes)
+            .ok_or_else(|| invalid_data("node out of bounds"))?;
+
+        let be = |bytes: &[u8]| bytes.iter().fold(0usize, |acc, &x| (acc << 8) | x as usize);
+        let record = match (self.record_size, bit) {
+            (24, 0) => be(&b[0..3]),
+            (24, _) => be(&b[3..6]),
+            (28, 0) => ((b[3] as usize & 0xF0) << 20) | be(&b[0..3]),
+            (28, _) => ((b[3] as usize & 0x0F) << 24) | be(&b[4..7]),
+            (_, 0) => be(&b[0..4]),
+            (_, _) => be(&b[4..8]),
+        };
+        Ok(record)
+    }
+
+    /// Look up the record for an IP address, if the database has one
+    pub fn lookup(&self, ip: IpAddr) -> io::Result<Option<MmdbValue>> {
+        let (bytes, start_node): (Vec<u8>, usize) = match ip {
+            IpAddr::V4(v4) => (v4.octets().to_vec(), self.ipv4_start),
+            IpAddr::V6(v6) => {
+                if self.ip_version == 4 {
+                    return Ok(None);
+                }
+                (v6.octets().to_vec(), 0)
+            }
+        };
+
+        let mut node = start_node;
+        for i in 0..bytes.len() * 8 {
+            if node >= self.node_count {
+                break;
+            }
+            let bit = (bytes[i / 8] >> (7 - (i % 8))) & 1;
+            node = self.read_record(node, bit)?;
+        }
+
+        if node == self.node_count {
+            return Ok(None);
+        }
+        if node < self.node_count {
+            return Err(invalid_data("search tree too deep"));
+        }
+
+        let offset = node
+            .checked_sub(self.node_count + DATA_SEPARATOR_SIZE)
+            .ok_or_else(|| invalid_data("record points into the data separator"))?;
+        let decoder = Decoder { data: &self.data[self.data_start..] };
+        decoder.decode(offset).map(|(value, _)| Some(value))
+    }
+}
+
+/// Decoder for the MaxMind DB data section format
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn byte(&self, offset: usize) -> io::Result<u8> {
+        self.data
+            .get(offset)
+            .copied()
+            .ok_or_else(|| invalid_data("unexpected end of data"))
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> io::Result<&[u8]> {
+        self.data
+            .get(offset..offset + len)
+            .ok_or_else(|| invalid_data("unexpected end of data"))
+    }
+
+    fn uint(&self, offset: usize, len: usize) -> io::Result<u128> {
+        Ok(self
+            .slice(offset, len)?
+            .iter()
+            .fold(0u128, |acc, &b| (acc << 8) | b as u128))
+    }
+
+    /// Decode the value at `offset`, returning it and the offset just past it
+    fn decode(&self, offset: usize) -> io::Result<(MmdbValue, usize)> {
+        self.decode_nested(offset, 0)
+    }
+
+    fn decode_nested(&self, offset: usize, depth: usize) -> io::Result<(MmdbValue, usize)> {
+        if depth > MAX_DECODE_DEPTH {
+            return Err(invalid_data("data nested too deeply"));
+        }
+        let ctrl = self.byte(offset)?;
+        let mut pos = offset + 1;
+        let mut type_id = ctrl >> 5;
+
+        if type_id == 1 {
+            // Pointers resolve to another value but decoding resumes after the pointer
+            let ss = ((ctrl >> 3) & 0x3) as usize;
+            let vvv = (ctrl & 0x7) as usize;
+            let target = match ss {
+                0 => (vvv << 8) | self.uint(pos, 1)? as usize,
+                1 => ((vvv << 16) | self.uint(pos, 2)? as usize) + 2048,
+                2 => ((vvv << 24) | self.uint(pos, 3)? as usize) + 526_336,
+                _ => self.uint(pos, 4)? as usize,
+            };
+            let (value, _) = self.decode_nested(target, depth + 1)?;
+            return Ok((value, pos + ss + 1));
+        }
+
+        if type_id == 0 {
+            type_id = 7 + self.byte(pos)?;
+            pos += 1;
+        }
+
+        let mut size = (ctrl & 0x1f) as usize;
+        match size {
+            29 => {
+                size = 29 + self.uint(pos, 1)? as usize;
+                pos += 1;
+            }
+            30 => {
+                size = 285 + self.uint(pos, 2)? as usize;
+                pos += 2;
+            }
+            31 => {
+                size = 65_821 + self.uint(pos, 3)? as usize;
+                pos += 3;
+            }
+            _ => {}
+        }
+
+        match type_id {
+            2 => {
+                let s = std::str::from_utf8(self.slice(pos, size)?)
+                    .map_err(|_| invalid_data("invalid UTF-8 string"))?;
+                Ok((MmdbValue::String(s.to_string()), pos + size))
+            }
+            3 => {
+                let bits = self.uint(pos, 8)? as u64;
+                Ok((MmdbValue::Double(f64::from_bits(bits)), pos + 8))
+            }
+            4 => Ok((MmdbValue::Bytes(self.slice(pos, size)?.to_vec()), pos + size)),
+            5 | 6 | 9 => Ok((MmdbValue::Uint(self.uint(pos, size)? as u64), pos + size)),
+            10 => Ok((MmdbValue::Uint128(self.uint(pos, size)?), pos + size)),
+            8 => {
+                let raw = self.uint(pos, size)? as u32;
+                Ok((MmdbValue::Int(raw as i32), pos + size))
+            }
+            7 => {
+                let mut map = BTreeMap::new();
+                for _ in 0..size {
+                    let (key, next) = self.decode_nested(pos, depth + 1)?;
+                    let key = match key {
+                        MmdbValue::String(k) => k,
+                        _ => return Err(invalid_data("map key is not a string")),
+                    };
+                    let (value, next) = self.decode_nested(next, depth + 1)?;
+                    map.insert(key, value);
+                    pos = next;
+                }
+                Ok((MmdbValue::Map(map), pos))
+            }
+            11 => {
+                let mut items = Vec::with_capacity(size);
+                for _ in 0..size {
+                    let (value, next) = self.decode_nested(pos, depth + 1)?;
+                    items.push(value);
+                    pos = next;
+                }
+                Ok((MmdbValue::Array(items), pos))
+            }
+            14 => Ok((MmdbValue::Bool(size != 0), pos)),
+            15 => {
+                let bits = self.uint(pos, 4)? as u32;
+                Ok((MmdbValue::Float(f32::from_bits(bits)), pos + 4))
+            }
+            _ => Err(invalid_data("unsupported data type")),
+        }
+    }
+}
+
+/// Enriches log entries with country and ASN data from local GeoLite2 databases
+#[derive(Debug, Default)]
+pub struct GeoIpEnricher {
+    pub country_db: Option<MmdbReader>,
+    pub asn_db: Option<MmdbReader>,
+}
+
+impl GeoIpEnricher {
+    /// Open the given Country and/or ASN databases
+    pub fn open(country_path: Option<&Path>, asn_path: Option<&Path>) -> io::Result<Self> {
+        Ok(GeoIpEnricher {
+            country_db: country_path.map(MmdbReader::open).transpose()?,
+            asn_db: asn_path.map(MmdbReader::open).transpose()?,
+        })
+    }
+
+    /// Fill country, ASN and AS organisation from the client IP (first in the list)
+    pub fn enrich(&self, entry: &mut LogEntry) {
+        let ip = match entry.ips.iter().find_map(|ip| ip.parse::<IpAddr>().ok()) {
+            Some(ip) => ip,
+            None => return,
+        };
+
+        if let Some(Ok(Some(record))) = self.country_db.as_ref().map(|db| db.lookup(ip)) {
+            let iso_code = record
+                .get(&["country", "iso_code"])
+                .or_else(|| record.get(&["registered_country", "iso_code"]))
+                .and_then(MmdbValue::as_str);
+            if let Some(code) = iso_code {
+                entry.country = code.to_string();
+            }
+        }
+
+        if let Some(Ok(Some(record))) = self.asn_db.as_ref().map(|db| db.lookup(ip)) {
+            entry.asn = record
+                .get(&["autonomous_system_number"])
+                .and_then(MmdbValue::as_u64)
+                .map(|n| n as u32);
+            if let Some(org) = record
+                .get(&["autonomous_system_organization"])
+                .and_then(MmdbValue::as_str)
+            {
+                entry.as_org = org.to_string();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_util::TestLine;
+    use ipnetwork::Ipv4Network;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn encode_ctrl(type_id: u8, size: usize, out: &mut Vec<u8>) {
+        assert!(size < 285);
+        let size_bits = size.min(29) as u8;
+        if type_id <= 7 {
+            out.push((type_id << 5) | size_bits);
+        } else {
+            out.push(size_bits);
+            out.push(type_id - 7);
+        }
+        if size >= 29 {
+            out.push((size - 29) as u8);
+        }
+    }
+
+    fn encode(value: &MmdbValue, out: &mut Vec<u8>) {
+        match value {
+            MmdbValue::String(s) => {
+                encode_ctrl(2, s.len(), out);
+                out.extend_from_slice(s.as_bytes());
+            }
+            MmdbValue::Double(n) => {
+                encode_ctrl(3, 8, out);
+                out.extend_from_slice(&n.to_bits().to_be_bytes());
+            }
+            MmdbValue::Bytes(bytes) => {
+                encode_ctrl(4, bytes.len(), out);
+                out.extend_from_slice(bytes);
+            }
+            MmdbValue::Uint(n) => {
+                let bytes: Vec<u8> = n.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+                encode_ctrl(if *n > u32::MAX as u64 { 9 } else { 6 }, bytes.len(), out);
+                out.extend_from_slice(&bytes);
+            }
+            MmdbValue::Uint128(n) => {
+                let bytes: Vec<u8> = n.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+                encode_ctrl(10, bytes.len(), out);
+                out.extend_from_slice(&bytes);
+            }
+            MmdbValue::Int(n) => {
+                encode_ctrl(8, 4, out);
+                out.extend_from_slice(&n.to_be_bytes());
+            }
+            MmdbValue::Map(map) => {
+                encode_ctrl(7, map.len(), out);
+                for (key, value) in map {
+                    encode(&MmdbValue::String(key.clone()), out);
+                    encode(value, out);
+                }
+            }
+            MmdbValue::Array(items) => {
+                encode_ctrl(11, items.len(), out);
+                for item in items {
+                    encode(item, out);
+                }
+            }
+            MmdbValue::Bool(b) => encode_ctrl(14, *b as usize, out),
+            MmdbValue::Float(n) => {
+                encode_ctrl(15, 4, out);
+                out.extend_from_slice(&n.to_bits().to_be_bytes());
+            }
+        }
+    }
+
+    pub(crate) fn map(pairs: &[(&str, MmdbValue)]) -> MmdbValue {
+        MmdbValue::Map(pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect())
+    }
+
+    #[derive(Clone, Copy)]
+    enum Record {
+        Empty,
+        Node(usize),
+        Data(usize),
+    }
+
+    /// Build a minimal IPv4, 24-bit record MaxMind DB for tests
+    pub(crate) fn build_test_db(networks: &[(&str, MmdbValue)]) -> Vec<u8> {
+        let mut nodes: Vec<[Record; 2]> = vec![[Record::Empty; 2]];
+        let mut data = Vec::new();
+
+        for (cidr, value) in networks {
+            let network: Ipv4Network = cidr.parse().unwrap();
+            let offset = data.len();
+            encode(value, &mut data);
+
+            let addr = u32::from(network.network());
+            let mut node = 0;
+            for i in 0..network.prefix() {
+                let bit = ((addr >> (31 - i)) & 1) as usize;
+                if i + 1 == network.prefix() {
+                    nodes[node][bit] = Record::Data(offset);
+                } else {
+                    node = match nodes[node][bit] {
+                        Record::Node(next) => next,
+                        _ => {
+                            nodes.push([Record::Empty; 2]);
+                            let next = nodes.len() - 1;
+                            nodes[node][bit] = Record::Node(next);
+                            next
+                        }
+                    };
+                }
+            }
+        }
+
+        let node_count = nodes.len();
+        let mut out = Vec::new();
+        for node in &nodes {
+            for record in node {
+                let value = match record {
+                    Record::Empty => node_count,
+                    Record::Node(n) => *n,
+                    Record::Data(offset) => node_count + DATA_SEPARATOR_SIZE + offset,
+                };
+                out.extend_from_slice(&(value as u32).to_be_bytes()[1..]);
+            }
+        }
+        out.extend_from_slice(&[0u8; DATA_SEPARATOR_SIZE]);
+        out.extend_from_slice(&data);
+        out.extend_from_slice(METADATA_MARKER);
+        encode(
+            &map(&[
+                ("node_count", MmdbValue::Uint(node_count as u64)),
+                ("record_size", MmdbValue::Uint(24)),
+                ("ip_version", MmdbValue::Uint(4)),
+                ("database_type", MmdbValue::String("Test".to_string())),
+            ]),
+            &mut out,
+        );
+        out
+    }
+
+    pub(crate) fn write_test_dbs() -> (NamedTempFile, NamedTempFile) {
+        let country = build_test_db(&[
+            ("66.249.64.0/19", map(&[("country", map(&[("iso_code", MmdbValue::String("US".into()))]))])),
+            ("88.198.0.0/16", map(&[("country", map(&[("iso_code", MmdbValue::String("DE".into()))]))])),
+        ]);
+        let asn = build_test_db(&[
+            (
+                "66.249.64.0/19",
+                map(&[
+                    ("autonomous_system_number", MmdbValue::Uint(15169)),
+                    ("autonomous_system_organization", MmdbValue::String("GOOGLE".into())),
+                ]),
+            ),
+            (
+                "88.198.0.0/16",
+                map(&[
+                    ("autonomous_system_number", MmdbValue::Uint(24940)),
+                    ("autonomous_system_organization", MmdbValue::String("Hetzner Online GmbH".into())),
+                ]),
+            ),
+        ]);
+
+        let mut country_file = NamedTempFile::new().unwrap();
+        country_file.write_all(&country).unwrap();
+        let mut asn_file = NamedTempFile::new().unwrap();
+        asn_file.write_all(&asn).unwrap();
+        (country_file, asn_file)
+    }
+
+    #[test]
+    fn test_lookup() {
+        let db = MmdbReader::from_bytes(build_test_db(&[(
+            "10.0.0.0/8",
+            map(&[("name", MmdbValue::String("private".into()))]),
+        )]))
+        .unwrap();
+
+        let found = db.lookup("10.1.2.3".parse().unwrap()).unwrap().unwrap();
+        assert_eq!(found.get(&["name"]).and_then(MmdbValue::as_str), Some("private"));
+        assert!(db.lookup("11.0.0.1".parse().unwrap()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pointer_decoding() {
+        // Map with one key whose value is a pointer back to the string at offset 0
+        let mut data = Vec::new();
+        encode(&MmdbValue::String("shared".into()), &mut data);
+        let map_offset = data.len();
+        encode_ctrl(7, 1, &mut data);
+        encode(&MmdbValue::String("org".into()), &mut data);
+        data.extend_from_slice(&[0x20, 0x00]);
+
+        let decoder = Decoder { data: &data };
+        let (value, _) = decoder.decode(map_offset).unwrap();
+        assert_eq!(value.get(&["org"]).and_then(MmdbValue::as_str), Some("shared"));
+
+        let value = map(&[
+            ("double", MmdbValue::Double(1.5)),
+            ("bytes", MmdbValue::Bytes(vec![0, 1])),
+            ("uint64", MmdbValue::Uint(u64::MAX)),
+            ("uint128", MmdbValue::Uint128(u128::MAX)),
+            ("int", MmdbValue::Int(-7)),
+            ("array", MmdbValue::Array(vec![MmdbValue::Bool(true), MmdbValue::Float(0.5)])),
+        ]);
+        let mut data = Vec::new();
+        encode(&value, &mut data);
+        assert_eq!(Decoder { data: &data }.decode(0).unwrap().0, value);
+    }
+
+    #[test]
+    fn test_enrich_entry() {
+        let (country, asn) = write_test_dbs();
+        let enricher = GeoIpEnricher::open(Some(country.path()), Some(asn.path())).unwrap();
+
+        let line = TestLine::new("example.com", "/", 200, "curl/8.0").ip("88.198.10.20");
+        let mut entry = LogEntry::parse(&line.to_string()).unwrap();
+        enricher.enrich(&mut entry);
+
+        assert_eq!(entry.country, "DE");
+        assert_eq!(entry.asn, Some(24940));
+        assert_eq!(entry.as_org, "Hetzner Online GmbH");
+    }
+
+    #[test]
+    fn test_invalid_database() {
+        assert!(MmdbReader::from_bytes(b"not a database".to_vec()).is_err());
+
+        // A pointer to itself
+        let data = [0x20, 0x00];
+        assert!(Decoder { data: &data }.decode(0).is_err());
+
+        // A record pointing past the tree but before the data section
+        let mut db = build_test_db(&[("0.0.0.0/1", MmdbValue::Uint(1))]);
+        db[..3].copy_from_slice(&[0, 0, 2]);
+        let db = MmdbReader::from_bytes(db).unwrap();
+        assert!(db.lookup("1.2.3.4".parse().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// Counter for a monitored key. `count` overestimates the true frequency by at most `error`.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Counter {
+    pub count: u64,
+    pub error: u64,
+}
+
+/// Space-Saving heavy hitters summary: tracks approximate top-K keys in fixed memory.
+/// Summaries merge with the mergeable Space-Saving algorithm (Agarwal et al.).
+#[derive(Debug, Clone, Serialize)]
+pub struct SpaceSaving {
+    capacity: usize,
+    total: u64,
+    /// Monitored keys and their counters; evicted slots are reused
+    counters: Vec<(String, Counter)>,
+    /// Key -> slot in `counters`
+    #[serde(skip)]
+    slots: HashMap<String, usize>,
+    /// (count, slot) ordered index used to find the minimum counter
+    #[serde(skip)]
+    index: BTreeSet<(u64, usize)>,
+}
+
+/// A key reported by a heavy hitters summary, with its error bounds
+#[derive(Debug, Clone, Serialize)]
+pub struct HeavyHitter {
+    pub key: String,
+    /// Upper bound on the true count
+    pub count: u64,
+    /// Lower bound on the true count
+    pub guaranteed_count: u64,
+    pub error: u64,
+}
+
+/// Top-K report for the JSON output
+#[derive(Debug, Clone, Serialize)]
+pub struct HeavyHittersSummary {
+    pub capacity: usize,
+    pub total: u64,
+    /// Maximum count of any key missing from the summary
+    pub max_error: u64,
+    pub items: Vec<HeavyHitter>,
+}
+
+impl SpaceSaving {
+    /// Create a summary that monitors at most `capacity` keys
+    pub fn new(capacity: usize) -> Self {
+        SpaceSaving {
+            capacity: capacity.max(1),
+            total: 0,
+            counters: Vec::new(),
+            slots: HashMap::new(),
+            index: BTreeSet::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Number of monitored keys
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+
+    /// Smallest monitored count once the summary is full; bounds the count of unmonitored keys
+    pub fn max_error(&self) -> u64 {
+        if self.counters.len() < self.capacity {
+            0
+        } else {
+            self.index.first().map(|(count, _)| *count).unwrap_or(0)
+        }
+    }
+
+    pub fn insert(&mut self, key: &str) {
+        self.total += 1;
+
+        if let Some(&slot) = self.slots.get(key) {
+            let counter = &mut self.counters[slot].1;
+            self.index.remove(&(counter.count, slot));
+            counter.count += 1;
+            self.index.insert((counter.count, slot));
+            return;
+        }
+
+        let slot = if self.counters.len() < self.capacity {
+            self.counters.push((key.to_string(), Counter { count: 1, error: 0 }));
+            self.counters.len() - 1
+        } else {
+            // Replace the minimum counter; the new key inherits its count as error
+            let (min_count, slot) = self.index.pop_first().expect("full summary has counters");
+            let (min_key, _) = std::mem::replace(
+                &mut self.counters[slot],
+                (
+                    key.to_string(),
+                    Counter {
+                        count: min_count + 1,
+                        error: min_count,
+                    },
+                ),
+            );
+            self.slots.remove(&min_key);
+            slot
+        };
+        self.index.insert((self.counters[slot].1.count, slot));
+        self.slots.insert(key.to_string(), slot);
+    }
+
+    pub fn merge(&mut self, other: &SpaceSaving) {
+        let self_min = self.max_error();
+        let other_min = other.max_error();
+
+        let mut combined: HashMap<String, Counter> = HashMap::new();
+        for (key, counter) in &self.counters {
+            let theirs = other.slots.get(key).map(|&slot| &other.counters[slot].1);
+            combined.insert(
+                key.clone(),
+                Counter {
+                    count: counter.count + theirs.map_or(other_min, |c| c.count),
+                    error: counter.error + theirs.map_or(other_min, |c| c.error),
+                },
+            );
+        }
+        for (key, counter) in &other.counters {
+            if !self.slots.contains_key(key) {
+                combined.insert(
+                    key.clone(),
+                    Counter {
+                        count: counter.count + self_min,
+                        error: counter.error + self_min,
+                    },
+                );
+            }
+        }
+
+        let mut ranked: Vec<(String, Counter)> = combined.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(self.capacity);
+
+        self.total += other.total;
+        self.index = ranked.iter().enumerate().map(|(slot, (_, c))| (c.count, slot)).collect();
+        self.slots = ranked.iter().enumerate().map(|(slot, (key, _))| (key.clone(), slot)).collect();
+        self.counters = ranked;
+    }
+
+    /// The `n` most frequent keys with their error bounds
+    pub fn top(&self, n: usize) -> Vec<HeavyHitter> {
+        let mut ranked: Vec<&(String, Counter)> = self.counters.iter().collect();
+        ranked.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+        ranked
+            .into_iter()
+            .take(n)
+            .map(|(key, counter)| HeavyHitter {
+                key: key.clone(),
+                count: counter.count,
+                guaranteed_count: counter.count - counter.error,
+                error: counter.error,
+            })
+            .collect()
+    }
+
+    pub fn summary(&self, n: usize) -> HeavyHittersSummary {
+        HeavyHittersSummary {
+            capacity: self.capacity,
+            total: self.total,
+            max_error: self.max_error(),
+            items: self.top(n),
+        }
+    }
+}
+
+/// Cut `map` back to its `capacity` heaviest entries once it holds more than twice
+/// that many. Amortizes to constant work per insert; counts of dropped keys are lost.
+pub fn keep_heaviest<V>(map: &mut HashMap<String, V>, capacity: usize, weight: impl Fn(&V) -> u64) {
+    let capacity = capacity.max(1);
+    if map.len() <= capacity * 2 {
+        return;
+    }
+    let mut weights: Vec<u64> = map.values().map(&weight).collect();
+    let cut = weights.len() - capacity;
+    let cutoff = *weights.select_nth_unstable(cut).1;
+    // Keys tied at the cutoff fill whatever room the heavier keys leave
+    let mut ties = capacity - weights[cut + 1..].iter().filter(|&&w| w > cutoff).count();
+    map.retain(|_, value| {
+        let w = weight(value);
+        if w > cutoff {
+            true
+        } else if w == cutoff && ties > 0 {
+            ties -= 1;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_heavy_hitters_in_bounded_memory() {
+        let mut summary = SpaceSaving::new(10);
+        for i in 0..10_000 {
+            // Three popular URLs among a long tail of session-id URLs
+            let key = match i % 8 {
+                0 | 2 | 4 | 6 => "/".to_string(),
+                1 | 5 => "/products".to_string(),
+                3 => "/cart".to_string(),
+                _ => format!("/page?sid={}", i),
+            };
+            summary.insert(&key);
+        }
+
+        let top = summary.top(3);
+        let keys: Vec<_> = top.iter().map(|h| h.key.as_str()).collect();
+        assert_eq!(keys, vec!["/", "/products", "/cart"]);
+        for hitter in &top {
+            assert!(hitter.guaranteed_count <= hitter.count);
+        }
+        assert!(top[0].guaranteed_count <= 5000 && top[0].count >= 5000);
+        assert_eq!(summary.total(), 10_000);
+        assert!(summary.counters.len() <= 10);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left = SpaceSaving::new(3);
+        let mut right = SpaceSaving::new(3);
+        for key in ["/a", "/a", "/a", "/b", "/c"] {
+            left.insert(key);
+        }
+        for key in ["/a", "/d", "/d", "/d", "/d"] {
+            right.insert(key);
+        }
+        left.merge(&right);
+
+        let top = left.top(2);
+        assert_eq!(top[0].key, "/d");
+        assert_eq!(top[0].guaranteed_count, 4);
+        assert_eq!(top[1].key, "/a");
+        assert_eq!(top[1].count, 4);
+        assert_eq!(left.total(), 10);
+        assert!(left.max_error() >= 1);
+    }
+
+    #[test]
+    fn test_exact_when_under_capacity() {
+        let mut summary = SpaceSaving::new(100);
+        for key in ["/x", "/y", "/x"] {
+            summary.insert(key);
+        }
+        let report = summary.summary(10);
+        assert_eq!(report.max_error, 0);
+        assert_eq!(report.items[0].key, "/x");
+        assert_eq!(report.items[0].error, 0);
+    }
+
+    #[test]
+    fn test_keep_heaviest() {
+        let mut map: HashMap<String, u64> = (0..10).map(|i| (format!("/{}", i), i % 4)).collect();
+        keep_heaviest(&mut map, 5, |count| *count);
+        assert_eq!(map.len(), 10);
+
+        map.insert("/big".to_string(), 100);
+        keep_heaviest(&mut map, 4, |count| *count);
+        assert_eq!(map.len(), 4);
+        assert!(map.contains_key("/big"));
+        assert_eq!(map.values().filter(|&&count| count == 3).count(), 2);
+    }
+}
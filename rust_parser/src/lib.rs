@@ -1,7 +1,28 @@
 pub mod aggregator;
+pub mod anomaly;
+pub mod blocklist;
 pub mod bot_detector;
+pub mod cardinality;
+pub mod crawl_import;
+pub mod diff;
+pub mod geoip;
+pub mod heavy_hitters;
+pub mod normalizer;
 pub mod output;
 pub mod parser;
+pub mod path_tree;
+pub mod pipeline;
+pub mod quantile;
+pub mod rate_detector;
+pub mod resource_type;
+pub mod robots;
+pub mod robots_sim;
+pub mod segmenter;
+pub mod sitemap;
+pub mod status_consistency;
+pub mod threat_detector;
+pub mod time_bucket;
+pub mod time_series;
 
 #[cfg(feature = "cli")]
 pub mod file_reader;
@@ -9,28 +30,37 @@ pub mod file_reader;
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
+#[cfg(test)]
+pub(crate) mod test_util;
+
 #[cfg(feature = "cli")]
 use rayon::prelude::*;
 #[cfg(feature = "cli")]
+use chrono::NaiveDate;
+#[cfg(feature = "cli")]
 use std::path::Path;
 
 pub use aggregator::Statistics;
 pub use bot_detector::enrich_entry;
 pub use parser::LogEntry;
+pub use pipeline::Pipeline;
+#[cfg(feature = "cli")]
+use robots::RobotsTxt;
+#[cfg(feature = "cli")]
+use robots_sim::RobotsSimulation;
 
 #[cfg(feature = "cli")]
 use file_reader::read_all_lines;
 
 /// Process a single log file and return statistics
 #[cfg(feature = "cli")]
-pub fn process_file(path: &Path) -> Statistics {
-    let mut stats = Statistics::new();
+pub fn process_file(path: &Path, pipeline: &Pipeline) -> Statistics {
+    let mut stats = pipeline.new_statistics();
 
     match read_all_lines(path) {
         Ok(lines) => {
             for line in lines {
-                if let Some(mut entry) = LogEntry::parse(&line) {
-                    enrich_entry(&mut entry);
+                if let Some(entry) = pipeline.process_line(&line) {
                     stats.add_entry(&entry);
                 }
             }
@@ -43,47 +73,100 @@ pub fn process_file(path: &Path) -> Statistics {
     stats
 }
 
-/// Process a single log file in parallel (line-level parallelism)
+/// Fold the entries of every file into per-worker accumulators and merge them
+/// (file and line parallelism). Files that cannot be read are reported and skipped.
 #[cfg(feature = "cli")]
-pub fn process_file_parallel(path: &Path) -> Statistics {
-    match read_all_lines(path) {
-        Ok(lines) => {
-            lines
+fn fold_entries<T: Send>(
+    paths: &[&Path],
+    pipeline: &Pipeline,
+    empty: impl Fn() -> T + Sync + Send,
+    add: impl Fn(T, LogEntry) -> T + Sync + Send,
+    merge: impl Fn(T, T) -> T + Sync + Send,
+) -> T {
+    paths
+        .par_iter()
+        .map(|path| match read_all_lines(path) {
+            Ok(lines) => lines
                 .par_iter()
-                .filter_map(|line| {
-                    LogEntry::parse(line).map(|mut entry| {
-                        enrich_entry(&mut entry);
-                        entry
-                    })
-                })
-                .fold(Statistics::new, |mut stats, entry| {
-                    stats.add_entry(&entry);
-                    stats
-                })
-                .reduce(Statistics::new, Statistics::merge)
-        }
-        Err(e) => {
-            eprintln!("Error reading file {:?}: {}", path, e);
-            Statistics::new()
-        }
-    }
+                .filter_map(|line| pipeline.process_line(line))
+                .fold(&empty, &add)
+                .reduce(&empty, &merge),
+            Err(e) => {
+                eprintln!("Error reading file {:?}: {}", path, e);
+                empty()
+            }
+        })
+        .reduce(&empty, &merge)
+}
+
+/// Process a single log file in parallel (line-level parallelism)
+#[cfg(feature = "cli")]
+pub fn process_file_parallel(path: &Path, pipeline: &Pipeline) -> Statistics {
+    process_files(&[path], pipeline)
 }
 
 /// Process multiple log files in parallel (file-level parallelism)
 #[cfg(feature = "cli")]
-pub fn process_files(paths: &[&Path]) -> Statistics {
-    paths
-        .par_iter()
-        .map(|path| process_file_parallel(path))
-        .reduce(Statistics::new, Statistics::merge)
+pub fn process_files(paths: &[&Path], pipeline: &Pipeline) -> Statistics {
+    fold_entries(
+        paths,
+        pipeline,
+        || pipeline.new_statistics(),
+        |mut stats, entry| {
+            stats.add_entry(&entry);
+            stats
+        },
+        Statistics::merge,
+    )
+}
+
+/// Process multiple log files into two periods: entries dated (in local time) before
+/// `split` and entries from `split` on. Entries without a timestamp are skipped.
+#[cfg(feature = "cli")]
+pub fn process_files_split(
+    paths: &[&Path],
+    pipeline: &Pipeline,
+    split: NaiveDate,
+) -> (Statistics, Statistics) {
+    fold_entries(
+        paths,
+        pipeline,
+        || (pipeline.new_statistics(), pipeline.new_statistics()),
+        |(mut before, mut after), entry| {
+            match entry.datetime {
+                Some(dt) if dt.date_naive() < split => before.add_entry(&entry),
+                Some(_) => after.add_entry(&entry),
+                None => {}
+            }
+            (before, after)
+        },
+        |(before, after), other| (before.merge(other.0), after.merge(other.1)),
+    )
+}
+
+/// Replay the logs against a candidate robots.txt (file and line parallelism)
+#[cfg(feature = "cli")]
+pub fn simulate_robots(
+    paths: &[&Path],
+    pipeline: &Pipeline,
+    candidate: &RobotsTxt,
+    domain: Option<&str>,
+) -> RobotsSimulation {
+    fold_entries(
+        paths,
+        pipeline,
+        || RobotsSimulation::new(domain.map(str::to_string)),
+        |mut simulation, entry| {
+            simulation.add_entry(&entry, candidate);
+            simulation
+        },
+        RobotsSimulation::merge,
+    )
 }
 
 /// Parse a single line and return enriched entry (for testing/debugging)
 pub fn parse_line(line: &str) -> Option<LogEntry> {
-    LogEntry::parse(line).map(|mut entry| {
-        enrich_entry(&mut entry);
-        entry
-    })
+    Pipeline::new().process_line(line)
 }
 
 #[cfg(test)]
@@ -1,14 +1,34 @@
-use clap::Parser;
-use std::path::PathBuf;
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
-use log_parser::{output, process_files};
+use log_parser::blocklist::{self, BlocklistFormat, BlocklistOptions, BlocklistSource};
+use log_parser::cardinality::HyperLogLog;
+use log_parser::crawl_import::{self, CrawlData};
+use log_parser::geoip::GeoIpEnricher;
+use log_parser::normalizer::{TrailingSlash, UrlNormalizer};
+use log_parser::parser::ResponseTimeUnit;
+use log_parser::rate_detector::{RateConfig, UrlPattern};
+use log_parser::robots::{RobotsRules, RobotsTxt};
+use log_parser::segmenter::SegmentRules;
+use log_parser::sitemap::{self, Sitemap};
+use log_parser::time_bucket::TimeBucket;
+use log_parser::threat_detector::ThreatRules;
+use log_parser::{
+    diff, output, process_files, process_files_split, simulate_robots, Pipeline,
+};
 
 /// High-performance log parser with parallel processing and bot detection
 #[derive(Parser)]
 #[command(name = "log_parser")]
 #[command(about = "Parse server logs and output aggregated statistics as JSON")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Log files to process
     #[arg(required = true)]
     files: Vec<PathBuf>,
@@ -24,14 +44,252 @@ struct Args {
     /// Pretty print JSON output
     #[arg(short, long, default_value = "true")]
     pretty: bool,
+
+    /// Exit with status 3 when any traffic anomaly is found
+    #[arg(long)]
+    fail_on_anomaly: bool,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+
+    /// Write an IP blocklist in this format (nginx, apache, iptables, nftables, fail2ban)
+    #[arg(long, requires = "blocklist_output")]
+    blocklist: Option<BlocklistFormat>,
+
+    /// Blocklist output file
+    #[arg(long, requires = "blocklist")]
+    blocklist_output: Option<PathBuf>,
+
+    /// IP sources for the blocklist (attackers, bursts, spoofed)
+    #[arg(long, value_delimiter = ',', default_value = "attackers,bursts,spoofed")]
+    blocklist_sources: Vec<BlocklistSource>,
+
+    /// Collapse blocklist IPs into CIDRs
+    #[arg(long)]
+    blocklist_collapse: bool,
+
+    /// Never block these IPs/networks (repeatable)
+    #[arg(long = "blocklist-allow", value_name = "CIDR")]
+    blocklist_allow: Vec<ipnetwork::IpNetwork>,
+
+    /// Allow blocking Google's crawler ranges (excluded by default)
+    #[arg(long)]
+    blocklist_include_googlebot: bool,
+
+    /// Sitemap or sitemap index (.xml or .xml.gz) to check crawl coverage against (repeatable)
+    #[arg(long = "sitemap", value_name = "FILE", conflicts_with = "heavy_hitters")]
+    sitemaps: Vec<PathBuf>,
+
+    /// Bot whose crawl is compared with the sitemap
+    #[arg(long, value_name = "NAME", default_value = "Googlebot")]
+    sitemap_bot: String,
+
+    /// Site crawl CSV export (Screaming Frog, Sitebulb) to join onto the URL stats
+    #[arg(long, value_name = "FILE", conflicts_with = "heavy_hitters")]
+    crawl_csv: Option<PathBuf>,
+
+    /// Column of the crawl CSV holding the URL (default: Address or URL)
+    #[arg(long, value_name = "NAME", requires = "crawl_csv")]
+    crawl_url_column: Option<String>,
 }
 
-fn main() {
-    let args = Args::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// Compare two periods (two file sets, or one set split at a date) and output the
+    /// deltas as JSON
+    Compare(CompareArgs),
+    /// Replay the logs against a candidate robots.txt and report the bot traffic and
+    /// URLs it would disallow
+    RobotsSim(RobotsSimArgs),
+}
+
+#[derive(clap::Args)]
+struct RobotsSimArgs {
+    /// Log files to replay
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Candidate robots.txt; requests already disallowed by --robots are not counted
+    #[arg(long, value_name = "FILE")]
+    candidate: PathBuf,
+
+    /// Only replay requests to this domain
+    #[arg(long)]
+    domain: Option<String>,
+
+    /// Output file (stdout if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Number of items to include in each list (default: 100)
+    #[arg(short = 'n', long, default_value = "100")]
+    top: usize,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+}
+
+#[derive(clap::Args)]
+struct CompareArgs {
+    /// Log files of the earlier period
+    #[arg(long, num_args = 1.., value_name = "FILES", requires = "after")]
+    before: Vec<PathBuf>,
+
+    /// Log files of the later period
+    #[arg(long, num_args = 1.., value_name = "FILES", requires = "before")]
+    after: Vec<PathBuf>,
+
+    /// Log files to split into two periods with --split-date
+    #[arg(conflicts_with_all = ["before", "after"], requires = "split_date")]
+    files: Vec<PathBuf>,
+
+    /// First day (YYYY-MM-DD, local time) of the later period
+    #[arg(long, value_name = "DATE", requires = "files")]
+    split_date: Option<NaiveDate>,
+
+    /// Output file (stdout if not specified)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Number of items to include in each list (default: 100)
+    #[arg(short = 'n', long, default_value = "100")]
+    top: usize,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+}
+
+/// Options controlling enrichment and aggregation, shared by all commands
+#[derive(clap::Args)]
+struct PipelineArgs {
+    /// GeoLite2-Country (or compatible) .mmdb database for country enrichment
+    #[arg(long)]
+    geoip_country: Option<PathBuf>,
+
+    /// GeoLite2-ASN (or compatible) .mmdb database for ASN enrichment
+    #[arg(long)]
+    geoip_asn: Option<PathBuf>,
+
+    /// Threat detection rule file (`category  regex` per line) replacing the built-in rules
+    #[arg(long)]
+    threat_rules: Option<PathBuf>,
+
+    /// Detect per-IP request bursts; implied by the other rate options and by a
+    /// blocklist using the bursts source
+    #[arg(long)]
+    detect_bursts: bool,
+
+    /// Sliding window length in seconds for rate abuse detection (default: 300)
+    #[arg(long)]
+    rate_window: Option<i64>,
+
+    /// Maximum requests per window from a single IP (default: 1500)
+    #[arg(long)]
+    rate_threshold: Option<u64>,
+
+    /// Maximum requests per window from a single IP to one URL pattern (login,
+    /// xmlrpc, ...) (default: 20)
+    #[arg(long)]
+    pattern_threshold: Option<u64>,
+
+    /// Extra URL pattern to track per IP, as NAME=REGEX (repeatable)
+    #[arg(long = "rate-url-pattern", value_name = "NAME=REGEX")]
+    rate_url_patterns: Vec<String>,
+
+    /// Segment rule file (`name  scope  regex` per line, scope is path, query or url);
+    /// the first matching rule names the page type
+    #[arg(long, value_name = "FILE")]
+    segments: Option<PathBuf>,
+
+    /// robots.txt to check requests against, as DOMAIN=FILE (repeatable); `*=FILE`
+    /// applies to every domain without its own file
+    #[arg(long = "robots", value_name = "DOMAIN=FILE")]
+    robots: Vec<String>,
+
+    /// Normalize URLs before aggregation (lowercase path, drop tracking parameters,
+    /// sort parameters, strip fragment, decode unreserved escapes)
+    #[arg(long)]
+    normalize_urls: bool,
+
+    /// Keep the case of URL paths when normalizing
+    #[arg(long, requires = "normalize_urls")]
+    keep_url_case: bool,
+
+    /// Trailing slash policy when normalizing: keep, strip or add
+    #[arg(long, value_name = "POLICY", default_value = "strip", requires = "normalize_urls")]
+    trailing_slash: TrailingSlash,
 
-    // Validate input files exist
+    /// Comma-separated query parameters to remove when normalizing, replacing the
+    /// built-in tracking list (a trailing `*` matches by prefix, e.g. utm_*)
+    #[arg(long, value_name = "LIST", value_delimiter = ',', requires = "normalize_urls")]
+    tracking_params: Option<Vec<String>>,
+
+    /// Unit of response times logged without a suffix: s (nginx $request_time),
+    /// ms (IIS time-taken) or us (Apache %D)
+    #[arg(long, value_name = "UNIT", default_value = "s")]
+    response_time_unit: ResponseTimeUnit,
+
+    /// IANA timezone (e.g. Europe/Madrid) all timestamps are converted to before bucketing
+    #[arg(long, value_name = "TZ")]
+    timezone: Option<chrono_tz::Tz>,
+
+    /// Time series bucket size: minute, 5m, 15m, hour, day, week or month
+    #[arg(long, value_name = "SIZE", default_value = "hour")]
+    time_bucket: TimeBucket,
+
+    /// Add a per-domain dimension to the time series output
+    #[arg(long)]
+    time_series_by_domain: bool,
+
+    /// Robust z-score above which hourly/daily traffic is reported as an anomaly
+    #[arg(long, value_name = "SCORE", default_value = "3.5", value_parser = positive_f64)]
+    anomaly_threshold: f64,
+
+    /// Directory levels to roll URLs up to in the path tree and section table
+    #[arg(long, value_name = "N", default_value = "3")]
+    path_depth: usize,
+
+    /// Count unique URLs/IPs approximately (HyperLogLog) with this standard error, e.g. 0.01,
+    /// down to about 0.002. Only the distinct counters shrink: per-URL and per-IP stats stay exact,
+    /// so combine with --heavy-hitters to bound memory on very large logs
+    #[arg(long, value_name = "ERROR", value_parser = error_bound)]
+    approx_unique: Option<f64>,
+
+    /// Track only the approximate top URLs (overall and per bot) with this many counters each.
+    /// The exact per-URL reports (top URLs, status inconsistencies, path tree, URL variants,
+    /// crawl matrix) are left empty, and the error URL, attack, spoofed IP, query parameter
+    /// and robots.txt lists keep only their heaviest entries. Not available with --sitemap,
+    /// --crawl-csv or compare.
+    #[arg(long, value_name = "CAPACITY")]
+    heavy_hitters: Option<usize>,
+}
+
+/// Parse a finite number greater than zero
+fn positive_f64(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+        Ok(_) => Err(format!("{} is not a finite number greater than 0", s)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn error_bound(s: &str) -> Result<f64, String> {
+    let min = HyperLogLog::min_error();
+    match s.parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 && value < 1.0 && value >= min => Ok(value),
+        Ok(value) if value > 0.0 && value < min => Err(format!(
+            "{} is below the smallest standard error the sketch can deliver ({:.5})",
+            s, min
+        )),
+        Ok(_) => Err(format!("{} is not a finite number between 0 and 1", s)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Keep the paths that exist, warning about the others; exit when none is left
+fn existing_paths(files: &[PathBuf]) -> Vec<PathBuf> {
     let mut valid_paths: Vec<PathBuf> = Vec::new();
-    for path in &args.files {
+    for path in files {
         if path.exists() {
             valid_paths.push(path.clone());
         } else {
@@ -43,29 +301,298 @@ fn main() {
         eprintln!("Error: No valid input files provided");
         std::process::exit(1);
     }
+    valid_paths
+}
 
-    // Convert to path references for processing
-    let path_refs: Vec<&std::path::Path> = valid_paths.iter().map(|p| p.as_path()).collect();
+/// Build the enrichment pipeline, exiting when a rule file or database fails to load
+fn build_pipeline(args: &PipelineArgs) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    if args.geoip_country.is_some() || args.geoip_asn.is_some() {
+        match GeoIpEnricher::open(args.geoip_country.as_deref(), args.geoip_asn.as_deref()) {
+            Ok(geoip) => pipeline.geoip = Some(geoip),
+            Err(e) => {
+                eprintln!("Error loading GeoIP database: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    // Process all files in parallel
-    eprintln!("Processing {} file(s)...", path_refs.len());
-    let stats = process_files(&path_refs);
+    if let Some(path) = &args.threat_rules {
+        match ThreatRules::from_file(path) {
+            Ok(rules) => pipeline.threats = Some(rules),
+            Err(e) => {
+                eprintln!("Error loading threat rules {:?}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    // Convert to summary
-    let summary = stats.to_summary(args.top);
+    if !args.robots.is_empty() {
+        let mut rules = RobotsRules::default();
+        for spec in &args.robots {
+            let loaded = spec
+                .split_once('=')
+                .ok_or_else(|| "expected DOMAIN=FILE".to_string())
+                .and_then(|(domain, path)| {
+                    RobotsTxt::from_file(Path::new(path))
+                        .map(|robots| rules.insert(domain, robots))
+                        .map_err(|e| e.to_string())
+                });
+            if let Err(e) = loaded {
+                eprintln!("Error in --robots {:?}: {}", spec, e);
+                std::process::exit(1);
+            }
+        }
+        pipeline.robots = Some(rules);
+    }
 
-    // Output results
-    let result = if let Some(output_path) = args.output {
-        output::write_json_file(&summary, &output_path)
+    if args.normalize_urls {
+        let mut normalizer = UrlNormalizer {
+            lowercase: !args.keep_url_case,
+            trailing_slash: args.trailing_slash,
+            ..Default::default()
+        };
+        if let Some(params) = &args.tracking_params {
+            normalizer.strip_params = params.clone();
+        }
+        pipeline.normalizer = Some(normalizer);
+    }
+
+    if let Some(path) = &args.segments {
+        match SegmentRules::from_file(path) {
+            Ok(rules) => pipeline.segments = Some(rules),
+            Err(e) => {
+                eprintln!("Error loading segment rules {:?}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pipeline.stats_config.approx_unique_error = args.approx_unique;
+    pipeline.stats_config.heavy_hitters = args.heavy_hitters;
+    pipeline.stats_config.path_depth = args.path_depth;
+    pipeline.stats_config.time_bucket = args.time_bucket;
+    pipeline.stats_config.time_series_by_domain = args.time_series_by_domain;
+    pipeline.stats_config.anomaly.threshold = args.anomaly_threshold;
+    pipeline.timezone = args.timezone;
+    pipeline.response_time_unit = args.response_time_unit;
+
+    let rate_enabled = args.detect_bursts
+        || args.rate_window.is_some()
+        || args.rate_threshold.is_some()
+        || args.pattern_threshold.is_some()
+        || !args.rate_url_patterns.is_empty();
+    if !rate_enabled {
+        return pipeline;
+    }
+    let rate = pipeline.stats_config.rate.insert(RateConfig::default());
+    if let Some(window) = args.rate_window {
+        rate.window_secs = window;
+    }
+    if let Some(threshold) = args.rate_threshold {
+        rate.ip_threshold = threshold;
+    }
+    if let Some(threshold) = args.pattern_threshold {
+        rate.pattern_threshold = threshold;
+    }
+    for spec in &args.rate_url_patterns {
+        let parsed = spec
+            .split_once('=')
+            .ok_or_else(|| "expected NAME=REGEX".to_string())
+            .and_then(|(name, re)| {
+                regex::Regex::new(re)
+                    .map(|pattern| UrlPattern { name: name.to_string(), pattern })
+                    .map_err(|e| e.to_string())
+            });
+        match parsed {
+            Ok(url_pattern) => rate.url_patterns.push(url_pattern),
+            Err(e) => {
+                eprintln!("Error in --rate-url-pattern {:?}: {}", spec, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pipeline
+}
+
+/// Write JSON output to a file or stdout, exiting on failure
+fn write_output<T: Serialize>(value: &T, output_path: Option<&Path>) {
+    let result = if let Some(output_path) = output_path {
+        output::write_json_file(value, output_path)
             .map(|_| eprintln!("Output written to {:?}", output_path))
     } else {
-        output::write_json_stdout(&summary)
+        output::write_json_stdout(value)
     };
 
     if let Err(e) = result {
         eprintln!("Error writing output: {}", e);
         std::process::exit(1);
     }
+}
+
+fn compare_command(args: CompareArgs) {
+    if args.pipeline.heavy_hitters.is_some() {
+        eprintln!("Error: compare needs exact per-URL stats and does not support --heavy-hitters");
+        std::process::exit(1);
+    }
+    let pipeline = build_pipeline(&args.pipeline);
+
+    let (before, after) = match args.split_date {
+        Some(split) => {
+            let valid_paths = existing_paths(&args.files);
+            let path_refs: Vec<&Path> = valid_paths.iter().map(|p| p.as_path()).collect();
+            eprintln!("Processing {} file(s), split at {}...", path_refs.len(), split);
+            process_files_split(&path_refs, &pipeline, split)
+        }
+        None if !args.before.is_empty() => {
+            let before_paths = existing_paths(&args.before);
+            let after_paths = existing_paths(&args.after);
+            let before_refs: Vec<&Path> = before_paths.iter().map(|p| p.as_path()).collect();
+            let after_refs: Vec<&Path> = after_paths.iter().map(|p| p.as_path()).collect();
+            eprintln!(
+                "Processing {} + {} file(s)...",
+                before_refs.len(),
+                after_refs.len()
+            );
+            (
+                process_files(&before_refs, &pipeline),
+                process_files(&after_refs, &pipeline),
+            )
+        }
+        None => {
+            eprintln!("Error: compare needs --before/--after files or files with --split-date");
+            std::process::exit(1);
+        }
+    };
+
+    let diff = diff::compare(&before, &after, args.top);
+    write_output(&diff, args.output.as_deref());
+
+    let totals = &diff.totals;
+    eprintln!("\nComparison (before -> after):");
+    eprintln!(
+        "  Total requests: {} -> {}",
+        totals.total_requests.before, totals.total_requests.after
+    );
+    eprintln!(
+        "  Bot requests: {} -> {}",
+        totals.bot_requests.before, totals.bot_requests.after
+    );
+    eprintln!(
+        "  Verified Googlebot: {} -> {}",
+        totals.verified_googlebot_requests.before, totals.verified_googlebot_requests.after
+    );
+    eprintln!("  New URLs: {}", diff.new_urls_count);
+    eprintln!("  Disappeared URLs: {}", diff.disappeared_urls_count);
+    eprintln!("  Status changes: {}", diff.status_changes_count);
+}
+
+fn robots_sim_command(args: RobotsSimArgs) {
+    let pipeline = build_pipeline(&args.pipeline);
+    let candidate = match RobotsTxt::from_file(&args.candidate) {
+        Ok(robots) => robots,
+        Err(e) => {
+            eprintln!("Error loading candidate robots.txt {:?}: {}", args.candidate, e);
+            std::process::exit(1);
+        }
+    };
+
+    let valid_paths = existing_paths(&args.files);
+    let path_refs: Vec<&Path> = valid_paths.iter().map(|p| p.as_path()).collect();
+    eprintln!("Replaying {} file(s) against {:?}...", path_refs.len(), args.candidate);
+    let simulation = simulate_robots(&path_refs, &pipeline, &candidate, args.domain.as_deref());
+
+    let summary = simulation.summary(args.top);
+    write_output(&summary, args.output.as_deref());
+
+    eprintln!("\nrobots.txt simulation:");
+    eprintln!(
+        "  Bot hits disallowed: {} of {} ({:.1}%)",
+        summary.disallowed_bot_hits, summary.total_bot_hits, summary.disallowed_pct
+    );
+    eprintln!("  Bots affected: {}", summary.bots_count);
+    eprintln!("  Blocked URLs answering 200 to humans: {}", summary.important_urls_count);
+}
+
+fn main() {
+    let args = Args::parse();
+    match args.command {
+        Some(Command::Compare(compare)) => return compare_command(compare),
+        Some(Command::RobotsSim(simulation)) => return robots_sim_command(simulation),
+        None => {}
+    }
+
+    let valid_paths = existing_paths(&args.files);
+    let mut pipeline = build_pipeline(&args.pipeline);
+    if args.blocklist.is_some() && args.blocklist_sources.contains(&BlocklistSource::Bursts) {
+        pipeline.stats_config.rate.get_or_insert_with(RateConfig::default);
+    }
+
+    // Convert to path references for processing
+    let path_refs: Vec<&Path> = valid_paths.iter().map(|p| p.as_path()).collect();
+
+    // Process all files in parallel
+    eprintln!("Processing {} file(s)...", path_refs.len());
+    let stats = process_files(&path_refs, &pipeline);
+
+    // Write blocklist
+    if let (Some(format), Some(path)) = (args.blocklist, &args.blocklist_output) {
+        let options = BlocklistOptions {
+            collapse_cidrs: args.blocklist_collapse,
+            allowlist: args.blocklist_allow.clone(),
+            allow_googlebot: !args.blocklist_include_googlebot,
+            ..Default::default()
+        };
+        let ips = blocklist::select_ips(&stats, &args.blocklist_sources);
+        let networks = blocklist::build_blocklist(ips.iter().map(String::as_str), &options);
+        match output::write_blocklist_file(&networks, format, &options, path) {
+            Ok(()) => eprintln!("Blocklist ({} entries) written to {:?}", networks.len(), path),
+            Err(e) => {
+                eprintln!("Error writing blocklist: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Convert to summary
+    let mut summary = stats.to_summary(args.top);
+
+    if !args.sitemaps.is_empty() {
+        let sitemap_refs: Vec<&Path> = args.sitemaps.iter().map(|p| p.as_path()).collect();
+        match Sitemap::load_all(&sitemap_refs) {
+            Ok(sitemap) => {
+                summary.sitemap = Some(sitemap::coverage(
+                    &sitemap,
+                    &stats,
+                    &args.sitemap_bot,
+                    pipeline.normalizer.as_ref(),
+                    args.top,
+                ));
+            }
+            Err(e) => {
+                eprintln!("Error loading sitemap: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = &args.crawl_csv {
+        match CrawlData::from_file(
+            path,
+            args.crawl_url_column.as_deref(),
+            pipeline.normalizer.as_ref(),
+        ) {
+            Ok(crawl) => summary.crawl = Some(crawl_import::crawl_report(&crawl, &stats, args.top)),
+            Err(e) => {
+                eprintln!("Error loading crawl CSV {:?}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Output results
+    write_output(&summary, args.output.as_deref());
 
     // Print summary to stderr
     eprintln!("\nSummary:");
@@ -75,4 +602,41 @@ fn main() {
     eprintln!("  Bot requests: {} ({:.1}%)", summary.bot_requests, summary.bot_percentage);
     eprintln!("  Human requests: {}", summary.human_requests);
     eprintln!("  Verified Googlebot: {}", summary.verified_googlebot_requests);
+    eprintln!("  Attack probes: {}", summary.attacks.total);
+    if pipeline.stats_config.rate.is_some() {
+        eprintln!("  Rate bursts: {}", summary.bursts.len());
+    }
+    if let Some(latency) = &summary.latency {
+        eprintln!(
+            "  Latency p50/p90/p99: {:.3}s / {:.3}s / {:.3}s",
+            latency.p50, latency.p90, latency.p99
+        );
+    }
+    eprintln!("  Traffic anomalies: {}", summary.anomalies.len());
+    if pipeline.robots.is_some() {
+        eprintln!(
+            "  robots.txt violations: {} bot hits, {} blocked URLs with human traffic",
+            summary.robots.disallowed_hits, summary.robots.blocked_urls_with_human_traffic_count
+        );
+    }
+    if let Some(crawl) = &summary.crawl {
+        eprintln!(
+            "  Crawl join: {} orphan, {} uncrawled, {} bot hits on non-indexable URLs",
+            crawl.orphan_urls_count, crawl.uncrawled_urls_count, crawl.non_indexable_bot_hits
+        );
+    }
+    if let Some(coverage) = &summary.sitemap {
+        eprintln!(
+            "  Sitemap coverage ({}): {}/{} URLs ({:.1}%), {} crawled outside sitemap",
+            coverage.bot,
+            coverage.crawled_in_sitemap_count,
+            coverage.crawled_in_sitemap_count + coverage.not_crawled_in_sitemap_count,
+            coverage.coverage_pct,
+            coverage.crawled_outside_sitemap_count
+        );
+    }
+
+    if args.fail_on_anomaly && !summary.anomalies.is_empty() {
+        std::process::exit(3);
+    }
 }
@@ -0,0 +1,202 @@
+use std::str::FromStr;
+
+use crate::parser::LogEntry;
+
+/// Query parameters removed by default: campaign and click tracking
+const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "gclid", "gbraid", "wbraid", "fbclid", "msclkid", "dclid", "yclid", "mc_cid",
+    "mc_eid", "_ga", "_gl", "igshid", "ref_src",
+];
+
+/// What to do with a trailing slash on the path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingSlash {
+    Keep,
+    /// `/page/` -> `/page`
+    Strip,
+    /// `/page` -> `/page/`, except for paths ending in a file name with an extension
+    Add,
+}
+
+impl FromStr for TrailingSlash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(TrailingSlash::Keep),
+            "strip" => Ok(TrailingSlash::Strip),
+            "add" => Ok(TrailingSlash::Add),
+            _ => Err(format!("unknown trailing slash policy {:?} (expected keep, strip or add)", s)),
+        }
+    }
+}
+
+/// URL normalization stage; collapses variants of the same URL before aggregation
+#[derive(Debug, Clone)]
+pub struct UrlNormalizer {
+    /// Lowercase the path (the query string is left as is)
+    pub lowercase: bool,
+    pub trailing_slash: TrailingSlash,
+    /// Decode percent-escaped unreserved characters (`%70` -> `p`) and uppercase remaining escapes
+    pub decode_unreserved: bool,
+    /// Query parameters to remove; a trailing `*` matches by prefix (e.g. `utm_*`)
+    pub strip_params: Vec<String>,
+    pub sort_params: bool,
+    pub strip_fragment: bool,
+}
+
+impl Default for UrlNormalizer {
+    fn default() -> Self {
+        UrlNormalizer {
+            lowercase: true,
+            trailing_slash: TrailingSlash::Strip,
+            decode_unreserved: true,
+            strip_params: DEFAULT_TRACKING_PARAMS.iter().map(|p| p.to_string()).collect(),
+            sort_params: true,
+            strip_fragment: true,
+        }
+    }
+}
+
+impl UrlNormalizer {
+    /// Normalize a request URL (path, optional query and fragment)
+    pub fn normalize(&self, url: &str) -> String {
+        let (rest, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+
+        let mut path = if self.decode_unreserved {
+            decode_unreserved(path)
+        } else {
+            path.to_string()
+        };
+        if self.lowercase {
+            path = path.to_lowercase();
+        }
+        match self.trailing_slash {
+            TrailingSlash::Strip if path.len() > 1 && path.ends_with('/') => {
+                path.truncate(path.trim_end_matches('/').len().max(1));
+            }
+            TrailingSlash::Add if !path.ends_with('/') => {
+                let last_segment = path.rsplit('/').next().unwrap_or("");
+                if !last_segment.contains('.') {
+                    path.push('/');
+                }
+            }
+            _ => {}
+        }
+
+        let mut normalized = path;
+        if let Some(query) = query {
+            let mut params: Vec<String> = query
+                .split('&')
+                .filter(|param| !param.is_empty())
+                .filter(|param| !self.is_stripped(param.split('=').next().unwrap_or("")))
+                .map(|param| {
+                    if self.decode_unreserved {
+                        decode_unreserved(param)
+                    } else {
+                        param.to_string()
+                    }
+                })
+                .collect();
+            if self.sort_params {
+                params.sort();
+            }
+            if !params.is_empty() {
+                normalized.push('?');
+                normalized.push_str(&params.join("&"));
+            }
+        }
+
+        if let Some(fragment) = fragment.filter(|_| !self.strip_fragment) {
+            normalized.push('#');
+            normalized.push_str(fragment);
+        }
+        normalized
+    }
+
+    fn is_stripped(&self, name: &str) -> bool {
+        self.strip_params.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix),
+            None => name.eq_ignore_ascii_case(pattern),
+        })
+    }
+
+    /// Normalize the entry's URL, keeping the original in `raw_url` when it changed
+    pub fn enrich(&self, entry: &mut LogEntry) {
+        let normalized = self.normalize(&entry.url);
+        if normalized != entry.url {
+            entry.raw_url = std::mem::replace(&mut entry.url, normalized);
+        }
+    }
+}
+
+/// Decode escapes of RFC 3986 unreserved characters and uppercase the hex digits of
+/// the rest, so that equivalent spellings compare equal without changing meaning
+fn decode_unreserved(s: &str) -> String {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                let decoded = (hi << 4) | lo;
+                if decoded.is_ascii_alphanumeric() || b"-._~".contains(&decoded) {
+                    out.push(decoded);
+                } else {
+                    out.push(b'%');
+                    out.push(bytes[i + 1].to_ascii_uppercase());
+                    out.push(bytes[i + 2].to_ascii_uppercase());
+                }
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_normalization_collapses_variants() {
+        let normalizer = UrlNormalizer::default();
+        for url in ["/page", "/page/", "/Page?utm_source=x", "/page#frag", "/%70age", "/page?"] {
+            assert_eq!(normalizer.normalize(url), "/page", "{}", url);
+        }
+        assert_eq!(normalizer.normalize("/"), "/");
+        assert_eq!(
+            normalizer.normalize("/search?q=Shoes&gclid=abc&a=%2f&page=2"),
+            "/search?a=%2F&page=2&q=Shoes"
+        );
+    }
+
+    #[test]
+    fn test_configured_policies() {
+        let normalizer = UrlNormalizer {
+            lowercase: false,
+            trailing_slash: TrailingSlash::Add,
+            strip_params: vec!["sid".to_string()],
+            sort_params: false,
+            strip_fragment: false,
+            ..Default::default()
+        };
+        assert_eq!(normalizer.normalize("/Blog?b=1&sid=9&a=2#top"), "/Blog/?b=1&a=2#top");
+        assert_eq!(normalizer.normalize("/img/logo.png?utm_source=x"), "/img/logo.png?utm_source=x");
+        assert!("ADD".parse::<TrailingSlash>().is_ok());
+        assert!("both".parse::<TrailingSlash>().is_err());
+    }
+}
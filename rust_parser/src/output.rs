@@ -1,20 +1,35 @@
+use ipnetwork::IpNetwork;
+use serde::Serialize;
 use serde_json;
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
 use crate::aggregator::StatisticsSummary;
+use crate::blocklist::{render_blocklist, BlocklistFormat, BlocklistOptions};
 
 /// Write statistics to JSON file
-pub fn write_json_file(stats: &StatisticsSummary, path: &Path) -> io::Result<()> {
+pub fn write_json_file<T: Serialize>(stats: &T, path: &Path) -> io::Result<()> {
     let file = File::create(path)?;
     let writer = BufWriter::new(file);
     serde_json::to_writer_pretty(writer, stats)?;
     Ok(())
 }
 
+/// Write an IP blocklist in the given server configuration format
+pub fn write_blocklist_file(
+    networks: &[IpNetwork],
+    format: BlocklistFormat,
+    options: &BlocklistOptions,
+    path: &Path,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(render_blocklist(networks, format, options).as_bytes())?;
+    writer.flush()
+}
+
 /// Write statistics to stdout as JSON
-pub fn write_json_stdout(stats: &StatisticsSummary) -> io::Result<()> {
+pub fn write_json_stdout<T: Serialize>(stats: &T) -> io::Result<()> {
     let stdout = io::stdout();
     let handle = stdout.lock();
     let mut writer = BufWriter::new(handle);
@@ -54,4 +69,15 @@ mod tests {
         let content = std::fs::read_to_string(temp.path()).unwrap();
         assert!(content.contains("total_requests"));
     }
+
+    #[test]
+    fn test_write_blocklist_file() {
+        let networks = vec!["192.0.2.1/32".parse().unwrap()];
+        let temp = NamedTempFile::new().unwrap();
+        write_blocklist_file(&networks, BlocklistFormat::Nginx, &BlocklistOptions::default(), temp.path())
+            .unwrap();
+
+        let content = std::fs::read_to_string(temp.path()).unwrap();
+        assert!(content.contains("deny 192.0.2.1;"));
+    }
 }
@@ -2,15 +2,54 @@ use chrono::{DateTime, FixedOffset};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::Serialize;
+use std::str::FromStr;
 
 /// Compiled regex for parsing log lines
-/// Format: [server]:::[domain]:::ip1,ip2 - - [dd/Mon/yyyy:HH:MM:SS +0000] "METHOD /path HTTP/1.1" status bytes "referer" "user-agent" "extra-id"
+/// Format: [server]:::[domain]:::ip1,ip2 - - [dd/Mon/yyyy:HH:MM:SS +0000] "METHOD /path HTTP/1.1" status bytes "referer" "user-agent" "extra-id" [response-time] ["content-type"]
+///
+/// The optional trailing response time may carry a `s`, `ms` or `us` suffix; bare
+/// numbers are read in the caller's `ResponseTimeUnit` (seconds for nginx
+/// `$request_time`, milliseconds for IIS `time-taken`, microseconds for Apache `%D`).
+/// It may be followed by the quoted response content type (nginx
+/// `$sent_http_content_type`).
 static LOG_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(
-        r#"^\[([^\]]+)\]:::\[([^\]]+)\]:::([^\s]+)\s+-\s+-\s+\[([^\]]+)\]\s+"([A-Z]+)\s+([^\s]+)\s+([^"]+)"\s+(\d+)\s+(\d+)\s+"([^"]*)"\s+"([^"]*)"\s+"([^"]*)""#
+        r#"^\[([^\]]+)\]:::\[([^\]]+)\]:::([^\s]+)\s+-\s+-\s+\[([^\]]+)\]\s+"([A-Z]+)\s+([^\s]+)\s+([^"]+)"\s+(\d+)\s+(\d+)\s+"([^"]*)"\s+"([^"]*)"\s+"([^"]*)"(?:\s+"?(\d+(?:\.\d+)?)(ms|us|s)?"?)?(?:\s+"([A-Za-z0-9.+-]+/[^"]*)")?"#
     ).expect("Failed to compile log regex")
 });
 
+/// Unit of response times logged as bare numbers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseTimeUnit {
+    #[default]
+    Seconds,
+    Milliseconds,
+    Microseconds,
+}
+
+impl ResponseTimeUnit {
+    fn to_seconds(self, value: f64) -> f64 {
+        match self {
+            ResponseTimeUnit::Seconds => value,
+            ResponseTimeUnit::Milliseconds => value / 1_000.0,
+            ResponseTimeUnit::Microseconds => value / 1_000_000.0,
+        }
+    }
+}
+
+impl FromStr for ResponseTimeUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "s" => Ok(ResponseTimeUnit::Seconds),
+            "ms" => Ok(ResponseTimeUnit::Milliseconds),
+            "us" => Ok(ResponseTimeUnit::Microseconds),
+            _ => Err(format!("unknown response time unit {:?} (expected s, ms or us)", s)),
+        }
+    }
+}
+
 /// Represents a single parsed log entry
 #[derive(Debug, Clone, Serialize)]
 pub struct LogEntry {
@@ -21,9 +60,15 @@ pub struct LogEntry {
     pub datetime_str: String,
     pub method: String,
     pub url: String,
+    /// URL as logged, set when URL normalization rewrote `url`
+    pub raw_url: String,
     pub protocol: String,
     pub status_code: u16,
     pub bytes_sent: u64,
+    /// Time taken to serve the request, in seconds
+    pub response_time: Option<f64>,
+    /// Response MIME type, when the log format records it
+    pub content_type: String,
     pub referer: String,
     pub user_agent: String,
     pub extra_id: String,
@@ -31,11 +76,27 @@ pub struct LogEntry {
     pub bot_name: String,
     pub bot_category: String,
     pub verified_googlebot: bool,
+    pub googlebot_type: String,
+    pub country: String,
+    pub asn: Option<u32>,
+    pub as_org: String,
+    pub threat_category: String,
+    /// Page type from the segment rules, empty when segmentation is disabled
+    pub segment: String,
+    /// html, js, css, image, font, api, xml, media, document, text or other
+    pub resource_type: String,
+    /// Whether the domain's robots.txt allows the request, `None` when none applies
+    pub robots_allowed: Option<bool>,
 }
 
 impl LogEntry {
-    /// Parse a single log line into a LogEntry
+    /// Parse a single log line into a LogEntry, reading bare response times as seconds
     pub fn parse(line: &str) -> Option<LogEntry> {
+        Self::parse_with_unit(line, ResponseTimeUnit::Seconds)
+    }
+
+    /// Parse a single log line, reading bare response times in the given unit
+    pub fn parse_with_unit(line: &str, bare_unit: ResponseTimeUnit) -> Option<LogEntry> {
         let caps = LOG_REGEX.captures(line)?;
 
         let server = caps.get(1)?.as_str().to_string();
@@ -51,6 +112,16 @@ impl LogEntry {
         let user_agent = caps.get(11)?.as_str().to_string();
         let extra_id = caps.get(12)?.as_str().to_string();
 
+        // Optional response time, normalized to seconds
+        let response_time = caps.get(13).and_then(|m| m.as_str().parse::<f64>().ok()).map(|value| {
+            let unit = match caps.get(14) {
+                Some(suffix) => suffix.as_str().parse().unwrap_or(bare_unit),
+                None => bare_unit,
+            };
+            unit.to_seconds(value)
+        });
+        let content_type = caps.get(15).map(|m| m.as_str().to_string()).unwrap_or_default();
+
         // Parse IPs (comma-separated)
         let ips: Vec<String> = ips_str
             .split(',')
@@ -73,9 +144,12 @@ impl LogEntry {
             datetime_str,
             method,
             url,
+            raw_url: String::new(),
             protocol,
             status_code,
             bytes_sent,
+            response_time,
+            content_type,
             referer,
             user_agent,
             extra_id,
@@ -83,6 +157,14 @@ impl LogEntry {
             bot_name: String::new(),
             bot_category: String::new(),
             verified_googlebot: false,
+            googlebot_type: String::new(),
+            country: String::new(),
+            asn: None,
+            as_org: String::new(),
+            threat_category: String::new(),
+            segment: String::new(),
+            resource_type: String::new(),
+            robots_allowed: None,
         })
     }
 }
@@ -90,6 +172,7 @@ impl LogEntry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TestLine;
 
     #[test]
     fn test_parse_valid_line() {
@@ -103,6 +186,44 @@ mod tests {
         assert_eq!(entry.url, "/path/to/page");
         assert_eq!(entry.status_code, 200);
         assert_eq!(entry.bytes_sent, 1234);
+        assert_eq!(entry.response_time, None);
+    }
+
+    #[test]
+    fn test_parse_response_time() {
+        let parse = |extra: &str| {
+            let line = TestLine::new("example.com", "/", 200, "Mozilla/5.0").extra(extra);
+            LogEntry::parse(&line.to_string()).unwrap().response_time
+        };
+
+        assert_eq!(parse("0.250"), Some(0.25));
+        assert_eq!(parse(r#""1.5""#), Some(1.5));
+        assert_eq!(parse("125ms"), Some(0.125));
+        assert_eq!(parse("2500us"), Some(0.0025));
+
+        // Apache `%D` logs bare microseconds
+        let apache = r#"[s]:::[example.com]:::192.0.2.1 - - [01/Jan/2024:12:00:00 +0000] "GET / HTTP/1.1" 200 5120 "-" "Mozilla/5.0" "-" 234567"#;
+        let entry = LogEntry::parse_with_unit(apache, ResponseTimeUnit::Microseconds).unwrap();
+        assert_eq!(entry.response_time, Some(0.234567));
+        let entry = LogEntry::parse_with_unit(apache, ResponseTimeUnit::Milliseconds).unwrap();
+        assert_eq!(entry.response_time, Some(234.567));
+        let suffixed = apache.replace("234567", "0.5s");
+        let entry = LogEntry::parse_with_unit(&suffixed, ResponseTimeUnit::Microseconds).unwrap();
+        assert_eq!(entry.response_time, Some(0.5));
+    }
+
+    #[test]
+    fn test_parse_content_type() {
+        let parse = |extra: &str| {
+            let line = TestLine::new("example.com", "/", 200, "Mozilla/5.0").extra(extra);
+            LogEntry::parse(&line.to_string()).unwrap()
+        };
+
+        let entry = parse(r#"0.010 "text/html; charset=utf-8""#);
+        assert_eq!(entry.response_time, Some(0.01));
+        assert_eq!(entry.content_type, "text/html; charset=utf-8");
+        assert_eq!(parse(r#""image/webp""#).content_type, "image/webp");
+        assert_eq!(parse(r#""-""#).content_type, "");
     }
 
     #[test]
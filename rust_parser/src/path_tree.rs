@@ -0,0 +1,221 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::aggregator::{StatusClassCounts, UrlStats};
+
+/// Traffic rolled up for a directory and everything below it
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PathNode {
+    /// Directory path with a trailing slash, e.g. `/products/shoes/`
+    pub path: String,
+    pub depth: usize,
+    pub hits: u64,
+    pub bot_hits: u64,
+    pub human_hits: u64,
+    pub bytes_total: u64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+    /// Subdirectories keyed by path segment
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub children: BTreeMap<String, PathNode>,
+}
+
+/// One row of the flat section table
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionRow {
+    pub path: String,
+    pub depth: usize,
+    pub hits: u64,
+    pub bot_hits: u64,
+    pub human_hits: u64,
+    pub bytes_total: u64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+}
+
+impl PathNode {
+    fn add(&mut self, stats: &UrlStats) {
+        self.hits += stats.hits;
+        self.bot_hits += stats.bot_hits;
+        self.human_hits += stats.human_hits;
+        self.bytes_total += stats.bytes_total;
+        for (code, count) in &stats.status_codes {
+            self.status_classes.add_count(*code, *count);
+        }
+    }
+
+    /// Keep only the `max_nodes` busiest directories below this one. A directory has
+    /// no more hits than its parent, so the kept nodes always hang off kept parents.
+    pub fn prune(&mut self, max_nodes: usize) {
+        let mut ranked: Vec<SectionRow> = Vec::new();
+        for child in self.children.values() {
+            child.collect_sections(&mut ranked);
+        }
+        if ranked.len() <= max_nodes {
+            return;
+        }
+        ranked.sort_by(|a, b| {
+            b.hits
+                .cmp(&a.hits)
+                .then_with(|| a.depth.cmp(&b.depth))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        let kept: HashSet<String> = ranked
+            .into_iter()
+            .take(max_nodes)
+            .map(|row| row.path)
+            .collect();
+        self.retain(&kept);
+    }
+
+    fn retain(&mut self, kept: &HashSet<String>) {
+        self.children.retain(|_, child| kept.contains(&child.path));
+        for child in self.children.values_mut() {
+            child.retain(kept);
+        }
+    }
+
+    /// Flatten the tree into section rows, busiest first
+    pub fn sections(&self) -> Vec<SectionRow> {
+        let mut rows = Vec::new();
+        self.collect_sections(&mut rows);
+        rows.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.path.cmp(&b.path)));
+        rows
+    }
+
+    fn collect_sections(&self, rows: &mut Vec<SectionRow>) {
+        rows.push(SectionRow {
+            path: self.path.clone(),
+            depth: self.depth,
+            hits: self.hits,
+            bot_hits: self.bot_hits,
+            human_hits: self.human_hits,
+            bytes_total: self.bytes_total,
+            status_classes: self.status_classes,
+        });
+        for child in self.children.values() {
+            child.collect_sections(rows);
+        }
+    }
+}
+
+/// Roll per-URL stats up into a directory tree of at most `max_depth` levels below
+/// the root. The last path segment is treated as a page unless the URL ends with `/`.
+pub fn build_path_tree(hits_by_url: &HashMap<String, UrlStats>, max_depth: usize) -> PathNode {
+    let mut root = PathNode {
+        path: "/".to_string(),
+        ..Default::default()
+    };
+
+    for (url, stats) in hits_by_url {
+        let path = url.split(['?', '#']).next().unwrap_or("");
+        let mut segments: Vec<&str> = path.split('/').collect();
+        // Drop the page name (empty for directory URLs)
+        segments.pop();
+
+        root.add(stats);
+        let mut node = &mut root;
+        for segment in segments
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .take(max_depth)
+        {
+            let parent_path = node.path.clone();
+            let depth = node.depth + 1;
+            node = node
+                .children
+                .entry(segment.to_string())
+                .or_insert_with(|| PathNode {
+                    path: format!("{}{}/", parent_path, segment),
+                    depth,
+                    ..Default::default()
+                });
+            node.add(stats);
+        }
+    }
+
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url_stats(hits: u64, bot_hits: u64, status: u16) -> UrlStats {
+        UrlStats {
+            hits,
+            bot_hits,
+            human_hits: hits - bot_hits,
+            bytes_total: hits * 100,
+            status_codes: HashMap::from([(status, hits)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rollup_to_depth() {
+        let hits_by_url = HashMap::from([
+            ("/".to_string(), url_stats(5, 1, 200)),
+            ("/blog/".to_string(), url_stats(3, 3, 200)),
+            ("/blog/post-1?ref=x".to_string(), url_stats(2, 0, 200)),
+            (
+                "/products/shoes/nike/air-max".to_string(),
+                url_stats(4, 4, 404),
+            ),
+            ("/products/shoes/adidas".to_string(), url_stats(1, 0, 200)),
+        ]);
+
+        let tree = build_path_tree(&hits_by_url, 2);
+        assert_eq!(tree.hits, 15);
+        assert_eq!(tree.children["blog"].hits, 5);
+        assert_eq!(tree.children["blog"].bot_hits, 3);
+        assert!(tree.children["blog"].children.is_empty());
+
+        let shoes = &tree.children["products"].children["shoes"];
+        assert_eq!(shoes.path, "/products/shoes/");
+        assert_eq!(shoes.depth, 2);
+        assert_eq!(shoes.hits, 5);
+        assert_eq!(shoes.status_classes.status_4xx, 4);
+        assert_eq!(shoes.bytes_total, 500);
+        assert!(shoes.children.is_empty());
+
+        let sections: Vec<_> = tree
+            .sections()
+            .into_iter()
+            .map(|r| (r.path, r.hits))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("/".to_string(), 15),
+                ("/blog/".to_string(), 5),
+                ("/products/".to_string(), 5),
+                ("/products/shoes/".to_string(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let hits_by_url = HashMap::from([
+            ("/a/x".to_string(), url_stats(3, 0, 200)),
+            ("/b/x".to_string(), url_stats(2, 0, 200)),
+            ("/c/x".to_string(), url_stats(1, 0, 200)),
+        ]);
+        let mut tree = build_path_tree(&hits_by_url, 3);
+        tree.prune(2);
+        assert_eq!(tree.children.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(tree.hits, 6);
+
+        // The budget covers the whole tree, not each level
+        let hits_by_url = HashMap::from([
+            ("/a/x/y/page".to_string(), url_stats(5, 0, 200)),
+            ("/a/z/page".to_string(), url_stats(2, 0, 200)),
+            ("/b/x/y/page".to_string(), url_stats(1, 0, 200)),
+        ]);
+        let mut tree = build_path_tree(&hits_by_url, 3);
+        tree.prune(4);
+        let paths: Vec<String> = tree.sections().into_iter().map(|row| row.path).collect();
+        assert_eq!(paths, vec!["/", "/a/", "/a/x/", "/a/x/y/", "/a/z/"]);
+    }
+}
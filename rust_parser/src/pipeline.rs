@@ -0,0 +1,99 @@
+#[cfg(feature = "cli")]
+use chrono_tz::Tz;
+
+use crate::aggregator::{Statistics, StatsConfig};
+use crate::bot_detector::enrich_entry;
+use crate::geoip::GeoIpEnricher;
+use crate::normalizer::UrlNormalizer;
+use crate::parser::{LogEntry, ResponseTimeUnit};
+use crate::resource_type;
+use crate::robots::RobotsRules;
+use crate::segmenter::SegmentRules;
+use crate::threat_detector::ThreatRules;
+#[cfg(feature = "cli")]
+use crate::time_bucket::convert_timezone;
+
+/// Per-entry processing stages applied between parsing and aggregation
+#[derive(Debug)]
+pub struct Pipeline {
+    /// Unit of response times logged without a suffix
+    pub response_time_unit: ResponseTimeUnit,
+    /// Timezone all timestamps are converted to before aggregation
+    #[cfg(feature = "cli")]
+    pub timezone: Option<Tz>,
+    pub geoip: Option<GeoIpEnricher>,
+    pub threats: Option<ThreatRules>,
+    /// robots.txt per domain, checked against every request
+    pub robots: Option<RobotsRules>,
+    pub normalizer: Option<UrlNormalizer>,
+    pub segments: Option<SegmentRules>,
+    pub stats_config: StatsConfig,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline {
+            response_time_unit: ResponseTimeUnit::default(),
+            #[cfg(feature = "cli")]
+            timezone: None,
+            geoip: None,
+            threats: Some(ThreatRules::default()),
+            robots: None,
+            normalizer: None,
+            segments: None,
+            stats_config: StatsConfig::default(),
+        }
+    }
+}
+
+impl Pipeline {
+    /// Create a pipeline with only the built-in enrichment stages
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create empty statistics using this pipeline's aggregation options
+    pub fn new_statistics(&self) -> Statistics {
+        Statistics::with_config(self.stats_config.clone())
+    }
+
+    /// Run all enrichment stages on a parsed entry
+    pub fn enrich(&self, entry: &mut LogEntry) {
+        #[cfg(feature = "cli")]
+        if let Some(tz) = self.timezone {
+            convert_timezone(entry, tz);
+        }
+
+        enrich_entry(entry);
+        resource_type::enrich(entry);
+
+        if let Some(geoip) = &self.geoip {
+            geoip.enrich(entry);
+        }
+
+        if let Some(threats) = &self.threats {
+            threats.enrich(entry);
+        }
+
+        if let Some(robots) = &self.robots {
+            robots.enrich(entry);
+        }
+
+        // Normalize last so threat and robots.txt rules see the URL as requested
+        if let Some(normalizer) = &self.normalizer {
+            normalizer.enrich(entry);
+        }
+
+        if let Some(segments) = &self.segments {
+            segments.enrich(entry);
+        }
+    }
+
+    /// Parse a single line and run it through the pipeline
+    pub fn process_line(&self, line: &str) -> Option<LogEntry> {
+        LogEntry::parse_with_unit(line, self.response_time_unit).map(|mut entry| {
+            self.enrich(&mut entry);
+            entry
+        })
+    }
+}
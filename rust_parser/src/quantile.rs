@@ -0,0 +1,188 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Default relative accuracy of quantile estimates (1%)
+const DEFAULT_RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Values at or below this are counted in the zero bucket
+const MIN_INDEXABLE_VALUE: f64 = 1e-9;
+
+/// DDSketch quantile sketch with bounded relative error. Bins are logarithmic,
+/// so two sketches with the same accuracy merge exactly by adding bin counts.
+#[derive(Debug, Clone, Serialize)]
+pub struct DdSketch {
+    relative_accuracy: f64,
+    #[serde(skip)]
+    gamma_ln: f64,
+    bins: BTreeMap<i32, u64>,
+    zero_count: u64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for DdSketch {
+    fn default() -> Self {
+        Self::new(DEFAULT_RELATIVE_ACCURACY)
+    }
+}
+
+impl DdSketch {
+    /// Create a sketch whose quantiles are within `relative_accuracy` of the true value
+    pub fn new(relative_accuracy: f64) -> Self {
+        let gamma = (1.0 + relative_accuracy) / (1.0 - relative_accuracy);
+        DdSketch {
+            relative_accuracy,
+            gamma_ln: gamma.ln(),
+            bins: BTreeMap::new(),
+            zero_count: 0,
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        if value <= MIN_INDEXABLE_VALUE {
+            self.zero_count += 1;
+        } else {
+            let index = (value.ln() / self.gamma_ln).ceil() as i32;
+            *self.bins.entry(index).or_insert(0) += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn merge(&mut self, other: &DdSketch) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other.clone();
+            return;
+        }
+        debug_assert_eq!(self.relative_accuracy, other.relative_accuracy);
+        for (index, count) in &other.bins {
+            *self.bins.entry(*index).or_insert(0) += count;
+        }
+        self.zero_count += other.zero_count;
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// Estimate the value at quantile `q` (0.0 - 1.0)
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+
+        let rank = (q * (self.count - 1) as f64).floor() as u64;
+        if rank < self.zero_count {
+            return Some(self.min.max(0.0));
+        }
+
+        let mut seen = self.zero_count;
+        for (index, count) in &self.bins {
+            seen += count;
+            if seen > rank {
+                let gamma = self.gamma_ln.exp();
+                let value = 2.0 * gamma.powi(*index) / (gamma + 1.0);
+                return Some(value.clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// Count, mean, p50/p90/p99 and max, or `None` when the sketch is empty
+    pub fn summary(&self) -> Option<LatencySummary> {
+        Some(LatencySummary {
+            count: self.count,
+            mean: self.mean()?,
+            p50: self.quantile(0.5)?,
+            p90: self.quantile(0.9)?,
+            p99: self.quantile(0.99)?,
+            max: self.max()?,
+        })
+    }
+}
+
+/// Latency percentiles in seconds
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles_within_accuracy() {
+        let mut sketch = DdSketch::default();
+        for i in 1..=1000 {
+            sketch.add(i as f64 / 1000.0);
+        }
+
+        for (q, expected) in [(0.5, 0.5), (0.9, 0.9), (0.99, 0.99)] {
+            let estimate = sketch.quantile(q).unwrap();
+            assert!((estimate - expected).abs() / expected <= 0.011, "q={} got {}", q, estimate);
+        }
+        assert_eq!(sketch.max(), Some(1.0));
+        assert_eq!(sketch.count(), 1000);
+    }
+
+    #[test]
+    fn test_merge_matches_single_sketch() {
+        let mut whole = DdSketch::default();
+        let mut left = DdSketch::default();
+        let mut right = DdSketch::default();
+        for i in 0..500 {
+            let value = (i % 37) as f64 * 0.013;
+            whole.add(value);
+            if i % 2 == 0 {
+                left.add(value);
+            } else {
+                right.add(value);
+            }
+        }
+        left.merge(&right);
+
+        assert_eq!(left.count(), whole.count());
+        for q in [0.1, 0.5, 0.9, 0.99] {
+            assert_eq!(left.quantile(q), whole.quantile(q));
+        }
+    }
+
+    #[test]
+    fn test_empty_sketch() {
+        let sketch = DdSketch::default();
+        assert!(sketch.quantile(0.5).is_none());
+        assert!(sketch.summary().is_none());
+    }
+}
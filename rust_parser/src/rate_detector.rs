@@ -0,0 +1,301 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parser::LogEntry;
+
+/// Maximum distinct URLs remembered per bucket, to bound memory on busy clients
+const MAX_URLS_PER_BUCKET: usize = 32;
+
+/// Built-in URL patterns tracked per client IP
+static DEFAULT_URL_PATTERNS: Lazy<Vec<UrlPattern>> = Lazy::new(|| {
+    let pattern = |name: &str, re: &str| UrlPattern {
+        name: name.to_string(),
+        pattern: Regex::new(re).expect("Failed to compile rate URL pattern"),
+    };
+    vec![
+        pattern("login", r"(?i)(/login|/signin|/wp-login\.php|/user/login|/admin/?$)"),
+        pattern("xmlrpc", r"(?i)/xmlrpc\.php"),
+    ]
+});
+
+/// A named URL pattern whose requests are tracked per client IP (e.g. login pages)
+#[derive(Debug, Clone)]
+pub struct UrlPattern {
+    pub name: String,
+    pub pattern: Regex,
+}
+
+/// Configuration for windowed rate analysis
+#[derive(Debug, Clone)]
+pub struct RateConfig {
+    /// Size of the mergeable fixed buckets counts are stored in
+    pub bucket_secs: i64,
+    /// Length of the sliding window that thresholds apply to
+    pub window_secs: i64,
+    /// Maximum requests per window from a single IP
+    pub ip_threshold: u64,
+    /// Maximum requests per window from a single IP to one URL pattern
+    pub pattern_threshold: u64,
+    pub url_patterns: Vec<UrlPattern>,
+}
+
+impl Default for RateConfig {
+    fn default() -> Self {
+        RateConfig {
+            bucket_secs: 60,
+            window_secs: 300,
+            ip_threshold: 1500,
+            pattern_threshold: 20,
+            url_patterns: DEFAULT_URL_PATTERNS.clone(),
+        }
+    }
+}
+
+/// Requests seen in one fixed time bucket
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WindowBucket {
+    pub count: u64,
+    pub urls: HashMap<String, u64>,
+}
+
+impl WindowBucket {
+    fn add_url(&mut self, url: &str, count: u64) {
+        self.count += count;
+        if let Some(n) = self.urls.get_mut(url) {
+            *n += count;
+        } else if self.urls.len() < MAX_URLS_PER_BUCKET {
+            self.urls.insert(url.to_string(), count);
+        }
+    }
+
+    fn merge(&mut self, other: &WindowBucket) {
+        let untracked = other.count - other.urls.values().sum::<u64>();
+        self.count += untracked;
+        for (url, count) in &other.urls {
+            self.add_url(url, *count);
+        }
+    }
+}
+
+/// Buckets keyed by start timestamp (Unix seconds)
+pub type BucketSeries = BTreeMap<i64, WindowBucket>;
+
+/// Per-client request counts in fixed buckets; merges by summing matching buckets
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RateWindows {
+    pub by_ip: HashMap<String, BucketSeries>,
+    /// URL pattern name -> client IP -> buckets
+    pub by_ip_pattern: HashMap<String, HashMap<String, BucketSeries>>,
+}
+
+impl RateWindows {
+    /// Record an entry in its client's bucket
+    pub fn add_entry(&mut self, entry: &LogEntry, config: &RateConfig) {
+        let (dt, ip) = match (entry.datetime, entry.ips.first()) {
+            (Some(dt), Some(ip)) => (dt, ip),
+            _ => return,
+        };
+        let bucket_secs = config.bucket_secs.max(1);
+        let ts = dt.timestamp();
+        let bucket = ts - ts.rem_euclid(bucket_secs);
+
+        self.by_ip
+            .entry(ip.clone())
+            .or_default()
+            .entry(bucket)
+            .or_default()
+            .add_url(&entry.url, 1);
+
+        for url_pattern in &config.url_patterns {
+            if url_pattern.pattern.is_match(&entry.url) {
+                self.by_ip_pattern
+                    .entry(url_pattern.name.clone())
+                    .or_default()
+                    .entry(ip.clone())
+                    .or_default()
+                    .entry(bucket)
+                    .or_default()
+                    .add_url(&entry.url, 1);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &RateWindows) {
+        for (ip, series) in &other.by_ip {
+            merge_series(self.by_ip.entry(ip.clone()).or_default(), series);
+        }
+        for (pattern, clients) in &other.by_ip_pattern {
+            let target = self.by_ip_pattern.entry(pattern.clone()).or_default();
+            for (ip, series) in clients {
+                merge_series(target.entry(ip.clone()).or_default(), series);
+            }
+        }
+    }
+
+    /// Find windows whose request count exceeds the configured thresholds
+    pub fn detect_bursts(&self, config: &RateConfig) -> Vec<Burst> {
+        let mut bursts = Vec::new();
+
+        for (ip, series) in &self.by_ip {
+            bursts.extend(find_bursts(ip, None, series, config, config.ip_threshold));
+        }
+        for (pattern, clients) in &self.by_ip_pattern {
+            for (ip, series) in clients {
+                bursts.extend(find_bursts(ip, Some(pattern), series, config, config.pattern_threshold));
+            }
+        }
+
+        bursts.sort_by(|a, b| {
+            b.peak_requests
+                .cmp(&a.peak_requests)
+                .then_with(|| a.start.cmp(&b.start))
+        });
+        bursts
+    }
+}
+
+fn merge_series(target: &mut BucketSeries, other: &BucketSeries) {
+    for (start, bucket) in other {
+        target.entry(*start).or_default().merge(bucket);
+    }
+}
+
+/// A period in which a client exceeded a rate threshold
+#[derive(Debug, Clone, Serialize)]
+pub struct Burst {
+    pub ip: String,
+    pub url_pattern: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub total_requests: u64,
+    /// Highest request count within a single sliding window
+    pub peak_requests: u64,
+    pub peak_rate_per_minute: f64,
+    pub urls: Vec<(String, u64)>,
+}
+
+fn find_bursts(
+    ip: &str,
+    url_pattern: Option<&str>,
+    series: &BucketSeries,
+    config: &RateConfig,
+    threshold: u64,
+) -> Vec<Burst> {
+    let bucket_secs = config.bucket_secs.max(1);
+    let window_secs = config.window_secs.max(bucket_secs);
+
+    // Flagged windows as (start, end, count), merged when they overlap
+    let mut spans: Vec<(i64, i64, u64)> = Vec::new();
+    for &start in series.keys() {
+        let end = start + window_secs;
+        let count: u64 = series.range(start..end).map(|(_, b)| b.count).sum();
+        if count <= threshold {
+            continue;
+        }
+        match spans.last_mut() {
+            Some(last) if start < last.1 => {
+                last.1 = end;
+                last.2 = last.2.max(count);
+            }
+            _ => spans.push((start, end, count)),
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|(start, end, peak)| {
+            let mut urls: HashMap<&str, u64> = HashMap::new();
+            let mut total = 0;
+            let mut last_bucket = start;
+            for (bucket_start, bucket) in series.range(start..end) {
+                total += bucket.count;
+                last_bucket = *bucket_start;
+                for (url, count) in &bucket.urls {
+                    *urls.entry(url).or_insert(0) += count;
+                }
+            }
+
+            let mut urls: Vec<(String, u64)> =
+                urls.into_iter().map(|(u, c)| (u.to_string(), c)).collect();
+            urls.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+            Burst {
+                ip: ip.to_string(),
+                url_pattern: url_pattern.map(str::to_string),
+                start: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+                end: DateTime::from_timestamp(last_bucket + bucket_secs, 0).unwrap_or_default(),
+                total_requests: total,
+                peak_requests: peak,
+                peak_rate_per_minute: peak as f64 * 60.0 / window_secs as f64,
+                urls,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestLine;
+
+    fn entry(ip: &str, time: &str, url: &str) -> LogEntry {
+        let line = TestLine::new("example.com", url, 200, "Mozilla/5.0")
+            .ip(ip)
+            .time(&format!("01/Jan/2024:{} +0000", time))
+            .method("POST");
+        LogEntry::parse(&line.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_detect_login_burst_across_merge() {
+        let config = RateConfig {
+            pattern_threshold: 5,
+            ..Default::default()
+        };
+
+        // Two workers each see part of the same attack
+        let mut first = RateWindows::default();
+        let mut second = RateWindows::default();
+        for i in 0..4 {
+            first.add_entry(&entry("203.0.113.9", &format!("10:00:{:02}", i), "/wp-login.php"), &config);
+            second.add_entry(&entry("203.0.113.9", &format!("10:01:{:02}", i), "/wp-login.php"), &config);
+        }
+        first.add_entry(&entry("198.51.100.1", "10:00:00", "/wp-login.php"), &config);
+
+        assert!(first.detect_bursts(&config).is_empty());
+        first.merge(&second);
+        let bursts = first.detect_bursts(&config);
+
+        assert_eq!(bursts.len(), 1);
+        let burst = &bursts[0];
+        assert_eq!(burst.ip, "203.0.113.9");
+        assert_eq!(burst.url_pattern.as_deref(), Some("login"));
+        assert_eq!(burst.total_requests, 8);
+        assert_eq!(burst.peak_requests, 8);
+        assert_eq!(burst.start.to_rfc3339(), "2024-01-01T10:00:00+00:00");
+        assert_eq!(burst.end.to_rfc3339(), "2024-01-01T10:02:00+00:00");
+        assert_eq!(burst.urls, vec![("/wp-login.php".to_string(), 8)]);
+    }
+
+    #[test]
+    fn test_ip_threshold() {
+        let config = RateConfig {
+            ip_threshold: 2,
+            window_secs: 60,
+            ..Default::default()
+        };
+        let mut windows = RateWindows::default();
+        for time in ["10:00:01", "10:00:02", "10:00:03", "10:05:00"] {
+            windows.add_entry(&entry("192.0.2.1", time, "/page"), &config);
+        }
+
+        let bursts = windows.detect_bursts(&config);
+        assert_eq!(bursts.len(), 1);
+        assert_eq!(bursts[0].url_pattern, None);
+        assert_eq!(bursts[0].peak_requests, 3);
+        assert_eq!(bursts[0].peak_rate_per_minute, 3.0);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::parser::LogEntry;
+
+/// Resource type for a MIME type such as `text/html; charset=utf-8`
+pub fn from_mime(content_type: &str) -> Option<&'static str> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    let (kind, subtype) = mime.split_once('/')?;
+
+    let resource_type = match (kind, subtype) {
+        ("text", "html") | ("application", "xhtml+xml") => "html",
+        ("text" | "application", "javascript" | "x-javascript" | "ecmascript") => "js",
+        ("text", "css") => "css",
+        ("image", _) => "image",
+        ("font", _) => "font",
+        ("application", s) if s.starts_with("font-") || s.starts_with("x-font-") => "font",
+        ("application", "vnd.ms-fontobject") => "font",
+        ("application", s) if s == "json" || s.ends_with("+json") => "api",
+        ("application" | "text", s) if s == "xml" || s.ends_with("+xml") => "xml",
+        ("video" | "audio", _) => "media",
+        ("application", "pdf" | "msword" | "rtf") => "document",
+        ("application", s) if s.starts_with("vnd.openxmlformats") || s.starts_with("vnd.ms-") => {
+            "document"
+        }
+        ("text", "plain") => "text",
+        _ => "other",
+    };
+    Some(resource_type)
+}
+
+/// Resource type for a file extension (without the dot)
+pub fn from_extension(extension: &str) -> &'static str {
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" | "shtml" | "xhtml" | "php" | "asp" | "aspx" | "jsp" => "html",
+        "js" | "mjs" => "js",
+        "css" => "css",
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "svg" | "ico" | "bmp" => "image",
+        "woff" | "woff2" | "ttf" | "otf" | "eot" => "font",
+        "json" => "api",
+        "xml" | "rss" | "atom" => "xml",
+        "mp4" | "webm" | "mov" | "mp3" | "ogg" | "wav" | "m4a" => "media",
+        "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "rtf" => "document",
+        "txt" => "text",
+        _ => "other",
+    }
+}
+
+/// Classify a request from its URL; extensionless paths are pages unless they
+/// look like API endpoints
+pub fn from_url(url: &str) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let last_segment = path.rsplit('/').next().unwrap_or("");
+
+    match last_segment.rsplit_once('.') {
+        Some((name, extension)) if !name.is_empty() && !extension.is_empty() => {
+            from_extension(extension)
+        }
+        _ if path.starts_with("/api/")
+            || path.contains("/wp-json/")
+            || path.starts_with("/graphql") =>
+        {
+            "api"
+        }
+        _ => "html",
+    }
+}
+
+/// Tag a log entry with its resource type, preferring the logged content type
+pub fn enrich(entry: &mut LogEntry) {
+    let resource_type = from_mime(&entry.content_type).unwrap_or_else(|| from_url(&entry.url));
+    entry.resource_type = resource_type.to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_url_and_mime() {
+        assert_eq!(from_url("/"), "html");
+        assert_eq!(from_url("/blog/post-1?ref=x"), "html");
+        assert_eq!(from_url("/static/app.min.JS?v=3"), "js");
+        assert_eq!(from_url("/img/logo.webp"), "image");
+        assert_eq!(from_url("/fonts/inter.woff2"), "font");
+        assert_eq!(from_url("/api/v1/products"), "api");
+        assert_eq!(from_url("/sitemap.xml"), "xml");
+        assert_eq!(from_url("/.env"), "html");
+
+        assert_eq!(from_mime("text/html; charset=utf-8"), Some("html"));
+        assert_eq!(from_mime("application/ld+json"), Some("api"));
+        assert_eq!(from_mime("application/javascript"), Some("js"));
+        assert_eq!(from_mime("application/x-font-woff"), Some("font"));
+        assert_eq!(from_mime(""), None);
+    }
+}
@@ -0,0 +1,362 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::RwLock;
+
+use crate::parser::LogEntry;
+
+/// User agent whose rules decide whether a URL visited by humans is blocked
+pub const HUMAN_TRAFFIC_AGENT: &str = "Googlebot";
+
+/// User agent strings whose selected group is cached per robots.txt
+const MAX_CACHED_AGENTS: usize = 10_000;
+
+/// A single `allow` or `disallow` line
+#[derive(Debug, Clone)]
+pub struct RobotsRule {
+    pub allow: bool,
+    /// Path pattern as written, used for longest-match precedence
+    pub pattern: String,
+    regex: Regex,
+}
+
+impl RobotsRule {
+    fn new(allow: bool, pattern: &str) -> Self {
+        let (body, anchored) = match pattern.strip_suffix('$') {
+            Some(body) => (body, true),
+            None => (pattern, false),
+        };
+        let mut source = String::from("^");
+        source.push_str(
+            &body
+                .split('*')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".*"),
+        );
+        if anchored {
+            source.push('$');
+        }
+        RobotsRule {
+            allow,
+            pattern: pattern.to_string(),
+            regex: Regex::new(&source).expect("escaped robots pattern"),
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+}
+
+/// Rules shared by one or more consecutive `user-agent` lines
+#[derive(Debug, Clone, Default)]
+pub struct RobotsGroup {
+    /// Lowercase product tokens, `*` for the default group
+    pub agents: Vec<String>,
+    pub rules: Vec<RobotsRule>,
+}
+
+/// A parsed robots.txt following Google's interpretation
+#[derive(Debug, Default)]
+pub struct RobotsTxt {
+    pub groups: Vec<RobotsGroup>,
+    pub sitemaps: Vec<String>,
+    /// Product token -> rules of every group naming it
+    rules_by_agent: HashMap<String, Vec<RobotsRule>>,
+    /// User agent string -> product token of the groups it follows
+    selected: RwLock<HashMap<String, String>>,
+}
+
+impl Clone for RobotsTxt {
+    fn clone(&self) -> Self {
+        RobotsTxt {
+            groups: self.groups.clone(),
+            sitemaps: self.sitemaps.clone(),
+            rules_by_agent: self.rules_by_agent.clone(),
+            selected: RwLock::default(),
+        }
+    }
+}
+
+/// Product token of a `user-agent` value: `Googlebot/2.1` -> `googlebot`
+fn agent_token(value: &str) -> String {
+    if value.starts_with('*') {
+        return "*".to_string();
+    }
+    value
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Lowercase product names of a user agent, the `name` of each `name/version`
+/// segment: `Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)`
+/// -> `mozilla`, `compatible`, `googlebot`
+fn product_names(user_agent: &str) -> Vec<String> {
+    user_agent
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ';' | ','))
+        .filter_map(|segment| {
+            let name = segment.split('/').next().unwrap_or("");
+            let is_token = name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            (!name.is_empty() && is_token).then(|| name.to_ascii_lowercase())
+        })
+        .collect()
+}
+
+/// Whether a product name is `token` or a `token-` variant, so `googlebot` matches
+/// `Googlebot-Image/1.0` but neither `Googlebot/2.1` matches `google` nor a URL in
+/// the user agent matches anything
+fn mentions_token(names: &[String], token: &str) -> bool {
+    names.iter().any(|name| {
+        name.strip_prefix(token)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+    })
+}
+
+impl RobotsTxt {
+    /// Parse robots.txt text. Unknown fields, rules outside a group and malformed
+    /// lines are ignored, as crawlers do.
+    pub fn parse(text: &str) -> Self {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut robots = RobotsTxt::default();
+        let mut current: Option<RobotsGroup> = None;
+        let mut in_agent_lines = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match field.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agent_lines {
+                        robots.groups.extend(current.take());
+                        current = Some(RobotsGroup::default());
+                    }
+                    in_agent_lines = true;
+                    let token = agent_token(value);
+                    if let (Some(group), false) = (current.as_mut(), token.is_empty()) {
+                        group.agents.push(token);
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_agent_lines = false;
+                    // An empty disallow allows everything, like having no rule
+                    if let (Some(group), false) = (current.as_mut(), value.is_empty()) {
+                        let allow = field.trim().eq_ignore_ascii_case("allow");
+                        group.rules.push(RobotsRule::new(allow, value));
+                    }
+                }
+                "sitemap" => robots.sitemaps.push(value.to_string()),
+                _ => {}
+            }
+        }
+        robots.groups.extend(current);
+
+        for group in &robots.groups {
+            for agent in &group.agents {
+                robots
+                    .rules_by_agent
+                    .entry(agent.clone())
+                    .or_default()
+                    .extend(group.rules.iter().cloned());
+            }
+        }
+        robots
+    }
+
+    /// Load robots.txt from a file
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Rules of every group for the most specific token among the user agent's
+    /// product names, falling back to the `*` groups. The choice is cached per
+    /// user agent string.
+    pub fn rules_for(&self, user_agent: &str) -> &[RobotsRule] {
+        let rules = |agent: &str| {
+            self.rules_by_agent
+                .get(agent)
+                .map_or(&[][..], Vec::as_slice)
+        };
+        let cache_full = {
+            let selected = self.selected.read().expect("robots cache lock");
+            if let Some(agent) = selected.get(user_agent) {
+                return rules(agent);
+            }
+            selected.len() >= MAX_CACHED_AGENTS
+        };
+
+        let names = product_names(user_agent);
+        let best = self
+            .rules_by_agent
+            .keys()
+            .filter(|agent| *agent != "*" && mentions_token(&names, agent))
+            .max_by(|a, b| a.len().cmp(&b.len()).then_with(|| b.cmp(a)))
+            .map(String::as_str)
+            .unwrap_or("*");
+        // Once the cache is full, lookups stay on the read lock
+        if !cache_full {
+            let mut selected = self.selected.write().expect("robots cache lock");
+            if selected.len() < MAX_CACHED_AGENTS {
+                selected.insert(user_agent.to_string(), best.to_string());
+            }
+        }
+        rules(best)
+    }
+
+    /// The rule deciding access to a URL: the longest matching pattern, `allow`
+    /// winning ties. `None` means no rule matches and the URL is allowed.
+    pub fn matching_rule(&self, user_agent: &str, url: &str) -> Option<&RobotsRule> {
+        let path = url.split('#').next().unwrap_or("");
+        if path == "/robots.txt" {
+            return None;
+        }
+        self.rules_for(user_agent)
+            .iter()
+            .filter(|rule| rule.matches(path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+    }
+
+    pub fn is_allowed(&self, user_agent: &str, url: &str) -> bool {
+        self.matching_rule(user_agent, url)
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// robots.txt files per domain, with an optional fallback for all other domains
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    pub by_domain: HashMap<String, RobotsTxt>,
+    pub default: Option<RobotsTxt>,
+}
+
+impl RobotsRules {
+    /// Add a robots.txt for a domain, or for every other domain when `domain` is `*`
+    pub fn insert(&mut self, domain: &str, robots: RobotsTxt) {
+        if domain == "*" {
+            self.default = Some(robots);
+        } else {
+            self.by_domain.insert(domain.to_ascii_lowercase(), robots);
+        }
+    }
+
+    pub fn get(&self, domain: &str) -> Option<&RobotsTxt> {
+        self.by_domain
+            .get(&domain.to_ascii_lowercase())
+            .or(self.default.as_ref())
+    }
+
+    /// Check the entry against its domain's robots.txt: bots under their own user
+    /// agent, humans under `HUMAN_TRAFFIC_AGENT`
+    pub fn enrich(&self, entry: &mut LogEntry) {
+        if let Some(robots) = self.get(&entry.domain) {
+            let user_agent = if entry.is_bot {
+                entry.user_agent.as_str()
+            } else {
+                HUMAN_TRAFFIC_AGENT
+            };
+            entry.robots_allowed = Some(robots.is_allowed(user_agent, &entry.url));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_line;
+
+    const ROBOTS: &str = r#"
+# Shop
+User-agent: *
+Disallow: /cart
+Disallow: /*?sort=
+Allow: /cart/help$
+
+User-agent: Googlebot
+User-agent: bingbot/2.0
+Disallow: /private/
+Allow: /private/press
+Disallow:
+
+user-agent: googlebot-image
+disallow: /
+
+Sitemap: https://example.com/sitemap.xml
+"#;
+
+    const GOOGLEBOT: &str =
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+
+    #[test]
+    fn test_groups_and_precedence() {
+        let robots = RobotsTxt::parse(ROBOTS);
+        assert_eq!(robots.groups.len(), 3);
+        assert_eq!(robots.groups[1].agents, vec!["googlebot", "bingbot"]);
+        assert_eq!(robots.sitemaps, vec!["https://example.com/sitemap.xml"]);
+
+        let other = "Mozilla/5.0 (compatible; AhrefsBot/7.0)";
+        assert!(!robots.is_allowed(other, "/cart/checkout"));
+        assert!(robots.is_allowed(other, "/cart/help"));
+        assert!(!robots.is_allowed(other, "/cart/help/more"));
+        assert!(!robots.is_allowed(other, "/shoes?sort=price&color=red"));
+        assert!(robots.is_allowed(other, "/private/"));
+
+        // Googlebot only follows its own group
+        assert!(robots.is_allowed(GOOGLEBOT, "/cart"));
+        assert!(!robots.is_allowed(GOOGLEBOT, "/private/docs"));
+        assert!(robots.is_allowed(GOOGLEBOT, "/private/press/2024"));
+        assert!(robots.is_allowed("Mozilla/5.0 (compatible; bingbot/2.0)", "/cart"));
+
+        // The most specific token wins over the broader googlebot group
+        let image = "Googlebot-Image/1.0";
+        assert!(!robots.is_allowed(image, "/logo.png"));
+        assert!(robots.is_allowed(image, "/robots.txt"));
+        // Cached selection gives the same answer
+        assert!(!robots.is_allowed(image, "/logo.png"));
+    }
+
+    #[test]
+    fn test_product_tokens_and_bom() {
+        let robots = RobotsTxt::parse(
+            "\u{feff}User-agent: google\nDisallow: /\n\nUser-agent: *\nDisallow: /cart\n",
+        );
+        assert_eq!(robots.groups[0].agents, vec!["google"]);
+
+        // A URL in the user agent is not a product token
+        let other = "Mozilla/5.0 (compatible; SomeBot/1.0; +http://www.google.com/bot.html)";
+        assert!(robots.is_allowed(other, "/page"));
+        assert!(!robots.is_allowed(other, "/cart"));
+        assert!(!robots.is_allowed("Google-InspectionTool/1.0", "/page"));
+        assert!(robots.is_allowed(GOOGLEBOT, "/page"));
+    }
+
+    #[test]
+    fn test_equal_length_allow_wins_and_domains() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /page\nAllow: /page\n");
+        assert!(robots.is_allowed("curl/8.0", "/page"));
+
+        let mut rules = RobotsRules::default();
+        rules.insert("Example.com", RobotsTxt::parse(ROBOTS));
+        rules.insert("*", RobotsTxt::parse("User-agent: *\nDisallow: /\n"));
+
+        let mut entry = LogEntry::parse(&test_line("example.com", "/cart", 200, GOOGLEBOT)).unwrap();
+        entry.is_bot = true;
+        rules.enrich(&mut entry);
+        assert_eq!(entry.robots_allowed, Some(true));
+
+        // Humans are checked with Googlebot's rules, on the fallback file here
+        let line = test_line("blog.example.org", "/cart", 200, "Mozilla/5.0");
+        let mut entry = LogEntry::parse(&line).unwrap();
+        rules.enrich(&mut entry);
+        assert_eq!(entry.robots_allowed, Some(false));
+    }
+}
@@ -0,0 +1,341 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::aggregator::{share, top_counts};
+use crate::parser::LogEntry;
+use crate::robots::RobotsTxt;
+use crate::sitemap::normalize_host;
+
+/// Bot traffic a candidate robots.txt would newly disallow
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedBotStats {
+    pub hits: u64,
+    pub disallowed_hits: u64,
+    /// Host -> URL -> newly disallowed hits
+    pub disallowed_urls: HashMap<String, HashMap<String, u64>>,
+    pub by_segment: HashMap<String, u64>,
+    pub by_status: HashMap<u16, u64>,
+}
+
+impl SimulatedBotStats {
+    pub fn merge(&mut self, other: &SimulatedBotStats) {
+        self.hits += other.hits;
+        self.disallowed_hits += other.disallowed_hits;
+        for (host, urls) in &other.disallowed_urls {
+            let target = self.disallowed_urls.entry(host.clone()).or_default();
+            for (url, count) in urls {
+                *target.entry(url.clone()).or_insert(0) += count;
+            }
+        }
+        for (segment, count) in &other.by_segment {
+            *self.by_segment.entry(segment.clone()).or_insert(0) += count;
+        }
+        for (status, count) in &other.by_status {
+            *self.by_status.entry(*status).or_insert(0) += count;
+        }
+    }
+}
+
+/// Effect of a candidate robots.txt on the traffic in the logs. Requests already
+/// disallowed by the current robots.txt (when one is configured) are not counted.
+#[derive(Debug, Clone, Default)]
+pub struct RobotsSimulation {
+    /// Only simulate requests to this domain, when set
+    pub domain: Option<String>,
+    pub by_bot: HashMap<String, SimulatedBotStats>,
+    /// Host -> URL -> human hits answered with 200, joined with the newly disallowed
+    /// bot URLs on the same host
+    pub human_ok_urls: HashMap<String, HashMap<String, u64>>,
+}
+
+impl RobotsSimulation {
+    pub fn new(domain: Option<String>) -> Self {
+        RobotsSimulation {
+            domain,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_entry(&mut self, entry: &LogEntry, candidate: &RobotsTxt) {
+        if let Some(domain) = &self.domain {
+            if !entry.domain.eq_ignore_ascii_case(domain) {
+                return;
+            }
+        }
+        // Rules apply to the URL as requested, before normalization
+        let requested = if entry.raw_url.is_empty() {
+            &entry.url
+        } else {
+            &entry.raw_url
+        };
+        let already_blocked = entry.robots_allowed == Some(false);
+
+        if entry.is_bot {
+            if entry.bot_name.is_empty() {
+                return;
+            }
+            let bot_stats = self.by_bot.entry(entry.bot_name.clone()).or_default();
+            bot_stats.hits += 1;
+            if !already_blocked && !candidate.is_allowed(&entry.user_agent, requested) {
+                bot_stats.disallowed_hits += 1;
+                *bot_stats
+                    .disallowed_urls
+                    .entry(normalize_host(&entry.domain))
+                    .or_default()
+                    .entry(entry.url.clone())
+                    .or_insert(0) += 1;
+                if !entry.segment.is_empty() {
+                    *bot_stats
+                        .by_segment
+                        .entry(entry.segment.clone())
+                        .or_insert(0) += 1;
+                }
+                *bot_stats.by_status.entry(entry.status_code).or_insert(0) += 1;
+            }
+        } else if entry.status_code == 200 {
+            *self
+                .human_ok_urls
+                .entry(normalize_host(&entry.domain))
+                .or_default()
+                .entry(entry.url.clone())
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub fn merge(mut self, other: Self) -> Self {
+        for (bot, stats) in &other.by_bot {
+            self.by_bot.entry(bot.clone()).or_default().merge(stats);
+        }
+        for (host, urls) in other.human_ok_urls {
+            let target = self.human_ok_urls.entry(host).or_default();
+            for (url, count) in urls {
+                *target.entry(url).or_insert(0) += count;
+            }
+        }
+        self
+    }
+
+    /// Report with the `top_n` bots, URLs and segments
+    pub fn summary(&self, top_n: usize) -> RobotsSimulationSummary {
+        let total_bot_hits: u64 = self.by_bot.values().map(|b| b.hits).sum();
+        let disallowed_bot_hits: u64 = self.by_bot.values().map(|b| b.disallowed_hits).sum();
+
+        let mut bots: Vec<(String, SimulatedBotSummary)> = self
+            .by_bot
+            .iter()
+            .filter(|(_, stats)| stats.disallowed_hits > 0)
+            .map(|(bot, stats)| {
+                // Top URLs sum the hits of a URL over every host
+                let mut by_url: HashMap<String, u64> = HashMap::new();
+                for (url, count) in stats.disallowed_urls.values().flatten() {
+                    *by_url.entry(url.clone()).or_insert(0) += count;
+                }
+                (
+                    bot.clone(),
+                    SimulatedBotSummary {
+                        hits: stats.hits,
+                        disallowed_hits: stats.disallowed_hits,
+                        disallowed_pct: share(stats.disallowed_hits, stats.hits),
+                        disallowed_url_count: stats
+                            .disallowed_urls
+                            .values()
+                            .map(HashMap::len)
+                            .sum(),
+                        by_segment: top_counts(&stats.by_segment, top_n),
+                        by_status: stats.by_status.iter().map(|(k, v)| (*k, *v)).collect(),
+                        top_urls: top_counts(&by_url, top_n),
+                    },
+                )
+            })
+            .collect();
+        let bots_count = bots.len();
+        bots.sort_by(|a, b| {
+            b.1.disallowed_hits
+                .cmp(&a.1.disallowed_hits)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        bots.truncate(top_n);
+
+        // URLs newly disallowed for any bot that humans still get a 200 on, per host
+        let mut important_urls: Vec<ImpactedUrl> = self
+            .human_ok_urls
+            .iter()
+            .flat_map(|(host, urls)| urls.iter().map(move |(url, hits)| (host, url, hits)))
+            .map(|(host, url, human_hits)| ImpactedUrl {
+                host: host.clone(),
+                url: url.clone(),
+                human_hits: *human_hits,
+                bot_hits: self
+                    .by_bot
+                    .values()
+                    .filter_map(|b| b.disallowed_urls.get(host)?.get(url))
+                    .sum(),
+            })
+            .filter(|impacted| impacted.bot_hits > 0)
+            .collect();
+        let important_urls_count = important_urls.len();
+        important_urls.sort_by(|a, b| {
+            b.human_hits
+                .cmp(&a.human_hits)
+                .then_with(|| b.bot_hits.cmp(&a.bot_hits))
+                .then_with(|| a.host.cmp(&b.host))
+                .then_with(|| a.url.cmp(&b.url))
+        });
+        important_urls.truncate(top_n);
+
+        RobotsSimulationSummary {
+            total_bot_hits,
+            disallowed_bot_hits,
+            disallowed_pct: share(disallowed_bot_hits, total_bot_hits),
+            bots_count,
+            bots,
+            important_urls_count,
+            important_urls,
+        }
+    }
+}
+
+/// Bot traffic lost to the candidate robots.txt
+#[derive(Debug, Serialize)]
+pub struct SimulatedBotSummary {
+    pub hits: u64,
+    pub disallowed_hits: u64,
+    /// Share of the bot's hits that would be disallowed, as a percentage
+    pub disallowed_pct: f64,
+    pub disallowed_url_count: usize,
+    pub by_segment: Vec<(String, u64)>,
+    pub by_status: BTreeMap<u16, u64>,
+    pub top_urls: Vec<(String, u64)>,
+}
+
+/// A URL the candidate would block for bots although it answers 200 to human visitors
+#[derive(Debug, Serialize)]
+pub struct ImpactedUrl {
+    /// Lowercase host without port and `www.`
+    pub host: String,
+    pub url: String,
+    pub human_hits: u64,
+    /// Bot hits on the URL that would be disallowed
+    pub bot_hits: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RobotsSimulationSummary {
+    pub total_bot_hits: u64,
+    pub disallowed_bot_hits: u64,
+    pub disallowed_pct: f64,
+    /// Bots losing at least one hit, before truncation to the top bots
+    pub bots_count: usize,
+    /// Bots losing the most hits first
+    pub bots: Vec<(String, SimulatedBotSummary)>,
+    pub important_urls_count: usize,
+    pub important_urls: Vec<ImpactedUrl>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use crate::robots::RobotsRules;
+    use crate::segmenter::SegmentRules;
+    use crate::test_util::test_line;
+
+    #[test]
+    fn test_simulate_candidate() {
+        let mut current = RobotsRules::default();
+        current.insert("*", RobotsTxt::parse("User-agent: *\nDisallow: /admin"));
+        let pipeline = Pipeline {
+            robots: Some(current),
+            segments: Some(
+                SegmentRules::parse("search  path  ^/search\nadmin  path  ^/admin").unwrap(),
+            ),
+            ..Default::default()
+        };
+        let candidate = RobotsTxt::parse(
+            "User-agent: *\nDisallow: /admin\nDisallow: /search\n\nUser-agent: Googlebot\nDisallow: /search\nDisallow: /*.pdf$",
+        );
+
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let bingbot = "Mozilla/5.0 (compatible; bingbot/2.0)";
+        let browser = "Mozilla/5.0 Chrome/120";
+
+        let mut left = RobotsSimulation::new(Some("example.com".to_string()));
+        let mut right = RobotsSimulation::new(Some("example.com".to_string()));
+        // Two partial simulations, as produced by parallel workers
+        for (first_half, entry) in [
+            (
+                true,
+                test_line("example.com", "/search?q=a", 200, googlebot),
+            ),
+            (
+                true,
+                test_line("example.com", "/docs/guide.pdf", 404, googlebot),
+            ),
+            (true, test_line("example.com", "/", 200, googlebot)),
+            (true, test_line("example.com", "/admin", 200, bingbot)),
+            (false, test_line("example.com", "/search?q=b", 200, bingbot)),
+            (
+                false,
+                test_line("example.com", "/docs/guide.pdf", 200, browser),
+            ),
+            (false, test_line("example.com", "/search?q=a", 500, browser)),
+            (false, test_line("example.com", "/search?q=c", 200, browser)),
+            (false, test_line("other.org", "/search?q=a", 200, googlebot)),
+        ] {
+            let sim = if first_half { &mut left } else { &mut right };
+            sim.add_entry(&pipeline.process_line(&entry).unwrap(), &candidate);
+        }
+        let summary = left.merge(right).summary(10);
+
+        assert_eq!(summary.total_bot_hits, 5);
+        // /admin was already disallowed for bingbot
+        assert_eq!(summary.disallowed_bot_hits, 3);
+        assert_eq!(summary.bots_count, 2);
+        let (name, googlebot) = &summary.bots[0];
+        assert_eq!(name, "Googlebot");
+        assert_eq!((googlebot.hits, googlebot.disallowed_hits), (3, 2));
+        assert_eq!(googlebot.by_status, BTreeMap::from([(200, 1), (404, 1)]));
+        assert_eq!(
+            googlebot.by_segment,
+            vec![("other".to_string(), 1), ("search".to_string(), 1)]
+        );
+        assert_eq!(
+            summary.bots[1].1.top_urls,
+            vec![("/search?q=b".to_string(), 1)]
+        );
+
+        // Neither the 500 on /search nor a page no bot requested is an important URL
+        assert_eq!(summary.important_urls_count, 1);
+        assert_eq!(summary.important_urls[0].url, "/docs/guide.pdf");
+        assert_eq!(summary.important_urls[0].bot_hits, 1);
+    }
+
+    #[test]
+    fn test_simulate_without_domain_joins_per_host() {
+        let pipeline = Pipeline::new();
+        let candidate = RobotsTxt::parse("User-agent: *\nDisallow: /search");
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let browser = "Mozilla/5.0 Chrome/120";
+
+        let mut simulation = RobotsSimulation::new(None);
+        for entry in [
+            test_line("example.com", "/search?q=a", 200, googlebot),
+            test_line("www.example.com", "/search?q=a", 200, browser),
+            test_line("other.org", "/search?q=b", 200, browser),
+            test_line("other.org", "/search?q=a", 200, googlebot),
+        ] {
+            simulation.add_entry(&pipeline.process_line(&entry).unwrap(), &candidate);
+        }
+        let summary = simulation.summary(1);
+
+        // The human hit on other.org/search?q=b has no bot hit on that host
+        assert_eq!(summary.important_urls_count, 1);
+        let impacted = &summary.important_urls[0];
+        assert_eq!(
+            (impacted.host.as_str(), impacted.url.as_str()),
+            ("example.com", "/search?q=a")
+        );
+        assert_eq!((impacted.human_hits, impacted.bot_hits), (1, 1));
+        assert_eq!(summary.bots[0].1.disallowed_url_count, 2);
+    }
+}
@@ -0,0 +1,159 @@
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::parser::LogEntry;
+
+/// Segment assigned to entries that match no rule
+pub const UNMATCHED_SEGMENT: &str = "other";
+
+/// Part of the URL a segment rule is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentScope {
+    /// Path without the query string
+    Path,
+    /// Query string without the leading `?` (empty when there is none)
+    Query,
+    /// Path and query string
+    Url,
+}
+
+impl FromStr for SegmentScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "path" => Ok(SegmentScope::Path),
+            "query" => Ok(SegmentScope::Query),
+            "url" => Ok(SegmentScope::Url),
+            _ => Err(format!(
+                "unknown segment scope {:?} (expected path, query or url)",
+                s
+            )),
+        }
+    }
+}
+
+/// A single page type rule
+#[derive(Debug, Clone)]
+pub struct SegmentRule {
+    pub name: String,
+    pub scope: SegmentScope,
+    pub pattern: Regex,
+}
+
+/// Ordered set of URL segmentation rules
+#[derive(Debug, Clone, Default)]
+pub struct SegmentRules {
+    pub rules: Vec<SegmentRule>,
+}
+
+impl SegmentRules {
+    /// Parse rules from text: `name  scope  regex` per line, `#` starts a comment.
+    /// Scope is `path`, `query` or `url`.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut rules = Vec::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |message: String| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", line_no + 1, message),
+                )
+            };
+
+            let mut fields = line.splitn(2, char::is_whitespace);
+            let name = fields.next().unwrap_or("");
+            let (scope, pattern) = fields
+                .next()
+                .map(str::trim_start)
+                .and_then(|rest| rest.split_once(char::is_whitespace))
+                .map(|(s, p)| (s, p.trim()))
+                .filter(|(_, p)| !p.is_empty())
+                .ok_or_else(|| invalid("expected `name scope regex`".to_string()))?;
+
+            rules.push(SegmentRule {
+                name: name.to_string(),
+                scope: scope.parse().map_err(invalid)?,
+                pattern: Regex::new(pattern).map_err(|e| invalid(e.to_string()))?,
+            });
+        }
+
+        Ok(SegmentRules { rules })
+    }
+
+    /// Load rules from a file
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Return the name of the first rule matching the URL
+    pub fn classify(&self, url: &str) -> Option<&str> {
+        let without_fragment = url.split('#').next().unwrap_or("");
+        let (path, query) = without_fragment
+            .split_once('?')
+            .unwrap_or((without_fragment, ""));
+
+        self.rules
+            .iter()
+            .find(|rule| {
+                let target = match rule.scope {
+                    SegmentScope::Path => path,
+                    SegmentScope::Query => query,
+                    SegmentScope::Url => without_fragment,
+                };
+                rule.pattern.is_match(target)
+            })
+            .map(|rule| rule.name.as_str())
+    }
+
+    /// Tag a log entry with its segment, or `other` when no rule matches
+    pub fn enrich(&self, entry: &mut LogEntry) {
+        entry.segment = self
+            .classify(&entry.url)
+            .unwrap_or(UNMATCHED_SEGMENT)
+            .to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+# Assets first so /products/img.jpg is not a product
+assets      path   \.(css|js|png|jpe?g|gif|svg|woff2?)$
+search      path   ^/search
+pagination  query  (^|&)page=\d+
+product     path   ^/products/[^/]+$
+category    path   ^/products/?$
+"#;
+
+    #[test]
+    fn test_classify_in_order() {
+        let rules = SegmentRules::parse(RULES).unwrap();
+        assert_eq!(rules.rules.len(), 5);
+        assert_eq!(rules.classify("/products/img.jpg"), Some("assets"));
+        assert_eq!(rules.classify("/search?q=shoes&page=2"), Some("search"));
+        assert_eq!(rules.classify("/products/?page=3"), Some("pagination"));
+        assert_eq!(rules.classify("/products/air-max#reviews"), Some("product"));
+        assert_eq!(rules.classify("/products"), Some("category"));
+        assert_eq!(rules.classify("/about"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = SegmentRules::parse("product  ^/p/").unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+        let err = SegmentRules::parse("# ok\nproduct  host  ^/p/").unwrap_err();
+        assert!(err.to_string().contains("line 2: unknown segment scope"));
+        assert!(SegmentRules::parse("product  path  ([").is_err());
+    }
+}
@@ -0,0 +1,564 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+#[cfg(feature = "cli")]
+use flate2::read::GzDecoder;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+#[cfg(feature = "cli")]
+use std::fs;
+use std::io;
+#[cfg(feature = "cli")]
+use std::io::Read;
+#[cfg(feature = "cli")]
+use std::path::Path;
+
+use crate::aggregator::{BotUrlStats, DomainCrawl, Statistics};
+use crate::normalizer::UrlNormalizer;
+use crate::resource_type;
+
+/// Magic bytes at the start of every gzip stream
+#[cfg(feature = "cli")]
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+static URL_BLOCK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<url\b[^>]*>(.*?)</url>").unwrap());
+static SITEMAP_BLOCK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<sitemap\b[^>]*>(.*?)</sitemap>").unwrap());
+static LOC: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<loc>(.*?)</loc>").unwrap());
+static LASTMOD: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<lastmod>(.*?)</lastmod>").unwrap());
+
+/// A page listed in a sitemap
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: Option<DateTime<FixedOffset>>,
+}
+
+/// Contents of a single sitemap file
+#[derive(Debug, Clone, PartialEq)]
+pub enum SitemapDocument {
+    /// `<urlset>` with page URLs
+    UrlSet(Vec<SitemapUrl>),
+    /// `<sitemapindex>` with the locations of child sitemaps
+    Index(Vec<String>),
+}
+
+/// Page URLs collected from one or more sitemaps and sitemap indexes
+#[derive(Debug, Clone, Default)]
+pub struct Sitemap {
+    pub urls: Vec<SitemapUrl>,
+    /// Child sitemaps referenced by an index but not found next to it
+    pub missing_sitemaps: Vec<String>,
+}
+
+/// Text content of an element, without CDATA wrapping and XML escapes
+fn element_text(raw: &str) -> String {
+    let text = raw.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+        .unwrap_or(text);
+    text.trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parse a W3C datetime (`2024-01-31`, `2024-01-31T10:00+01:00`, ...); dates
+/// without a time are taken as midnight UTC
+pub fn parse_lastmod(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt);
+    }
+    let with_offset = match value.strip_suffix('Z') {
+        Some(rest) => format!("{}+00:00", rest),
+        None => value.to_string(),
+    };
+    if let Ok(dt) = DateTime::parse_from_str(&with_offset, "%Y-%m-%dT%H:%M%:z") {
+        return Some(dt);
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d"))
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{}-01-01", value), "%Y-%m-%d"))
+        .ok()?;
+    Some(
+        Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .fixed_offset(),
+    )
+}
+
+/// Parse the XML of a sitemap or sitemap index
+pub fn parse_sitemap(xml: &str) -> io::Result<SitemapDocument> {
+    if xml.contains("<sitemapindex") {
+        let locs = SITEMAP_BLOCK
+            .captures_iter(xml)
+            .filter_map(|block| LOC.captures(&block[1]).map(|loc| element_text(&loc[1])))
+            .filter(|loc| !loc.is_empty())
+            .collect();
+        return Ok(SitemapDocument::Index(locs));
+    }
+    if xml.contains("<urlset") {
+        let urls = URL_BLOCK
+            .captures_iter(xml)
+            .filter_map(|block| {
+                let loc = element_text(&LOC.captures(&block[1])?[1]);
+                let lastmod = LASTMOD
+                    .captures(&block[1])
+                    .and_then(|lastmod| parse_lastmod(&element_text(&lastmod[1])));
+                (!loc.is_empty()).then_some(SitemapUrl { loc, lastmod })
+            })
+            .collect();
+        return Ok(SitemapDocument::UrlSet(urls));
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "not a sitemap: expected <urlset> or <sitemapindex>",
+    ))
+}
+
+/// Read a sitemap file, decompressing it when gzipped
+#[cfg(feature = "cli")]
+fn read_sitemap_file(path: &Path) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(GZIP_MAGIC) {
+        let mut xml = String::new();
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut xml)?;
+        Ok(xml)
+    } else {
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(feature = "cli")]
+impl Sitemap {
+    /// Load a sitemap (`.xml` or `.xml.gz`). For a sitemap index, child sitemaps are
+    /// looked up by file name in the index's directory.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut sitemap = Sitemap::default();
+        sitemap.load_into(path, &mut HashSet::new())?;
+        Ok(sitemap)
+    }
+
+    /// Load several sitemaps into one URL list
+    pub fn load_all(paths: &[&Path]) -> io::Result<Self> {
+        let mut sitemap = Sitemap::default();
+        let mut visited = HashSet::new();
+        for path in paths {
+            sitemap.load_into(path, &mut visited)?;
+        }
+        Ok(sitemap)
+    }
+
+    fn load_into(&mut self, path: &Path, visited: &mut HashSet<String>) -> io::Result<()> {
+        if !visited.insert(path.to_string_lossy().into_owned()) {
+            return Ok(());
+        }
+        let xml = read_sitemap_file(path)?;
+        let document = parse_sitemap(&xml)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+
+        match document {
+            SitemapDocument::UrlSet(urls) => self.urls.extend(urls),
+            SitemapDocument::Index(locs) => {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                for loc in locs {
+                    let file_name = loc
+                        .split(['?', '#'])
+                        .next()
+                        .unwrap_or("")
+                        .rsplit('/')
+                        .next();
+                    match file_name.map(|name| dir.join(name)).filter(|p| p.is_file()) {
+                        Some(child) => self.load_into(&child, visited)?,
+                        None => self.missing_sitemaps.push(loc),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Host (lowercase, without `www.`) and path with query of an absolute or
+/// root-relative sitemap URL
+pub(crate) fn split_loc(loc: &str) -> (String, &str) {
+    let Some((_, rest)) = loc.split_once("://") else {
+        return (String::new(), loc);
+    };
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    (normalize_host(host), path)
+}
+
+/// Lowercase host without port and `www.`
+pub(crate) fn normalize_host(host: &str) -> String {
+    let host = host.split(':').next().unwrap_or("").to_ascii_lowercase();
+    host.strip_prefix("www.")
+        .map(str::to_string)
+        .unwrap_or(host)
+}
+
+/// One URL in the coverage lists
+#[derive(Debug, Clone, Serialize)]
+pub struct SitemapUrlRow {
+    /// Host without `www.`, empty for root-relative sitemap URLs
+    pub host: String,
+    pub url: String,
+    pub lastmod: Option<DateTime<FixedOffset>>,
+    pub bot_hits: u64,
+    pub last_crawled: Option<DateTime<FixedOffset>>,
+    /// Days the page was modified after the bot last crawled it
+    pub days_stale: Option<i64>,
+}
+
+/// Join of the sitemap with one bot's crawl of the logs
+#[derive(Debug, Clone, Serialize)]
+pub struct SitemapCoverage {
+    pub bot: String,
+    pub sitemap_urls: usize,
+    /// Sitemap URLs on hosts that do not appear in the logs, left out of the join
+    pub other_domain_urls: usize,
+    pub missing_sitemaps: Vec<String>,
+    pub crawled_in_sitemap_count: usize,
+    pub not_crawled_in_sitemap_count: usize,
+    /// Crawled pages (HTML resources only) not listed in the sitemap
+    pub crawled_outside_sitemap_count: usize,
+    /// Share of sitemap URLs crawled by the bot, in percent
+    pub coverage_pct: f64,
+    pub stale_count: usize,
+    pub crawled_in_sitemap: Vec<SitemapUrlRow>,
+    pub not_crawled_in_sitemap: Vec<SitemapUrlRow>,
+    pub crawled_outside_sitemap: Vec<SitemapUrlRow>,
+    /// Crawled sitemap URLs whose `lastmod` is newer than the last crawl
+    pub stale: Vec<SitemapUrlRow>,
+}
+
+/// Compare the sitemap with the URLs crawled by `bot`. Sitemap URLs are reduced to
+/// host and path with query (normalized like the logs when a normalizer is given) and
+/// joined on both, so a path crawled on one domain does not cover another domain's page.
+/// Root-relative sitemap URLs match the path on any domain.
+pub fn coverage(
+    sitemap: &Sitemap,
+    stats: &Statistics,
+    bot: &str,
+    normalizer: Option<&UrlNormalizer>,
+    top_n: usize,
+) -> SitemapCoverage {
+    let log_hosts: HashSet<String> = stats
+        .hits_by_domain
+        .keys()
+        .map(|d| normalize_host(d))
+        .collect();
+    let empty = HashMap::new();
+    let crawled: &HashMap<String, BotUrlStats> = stats.hits_by_bot_url.get(bot).unwrap_or(&empty);
+
+    // Host -> URL key -> newest lastmod
+    let mut listed: HashMap<String, HashMap<String, Option<DateTime<FixedOffset>>>> =
+        HashMap::new();
+    let mut other_domain_urls = 0;
+    for url in &sitemap.urls {
+        let (host, path) = split_loc(&url.loc);
+        if !host.is_empty() && !log_hosts.is_empty() && !log_hosts.contains(&host) {
+            other_domain_urls += 1;
+            continue;
+        }
+        let key = match normalizer {
+            Some(normalizer) => normalizer.normalize(path),
+            None => path.to_string(),
+        };
+        let lastmod = listed
+            .entry(host)
+            .or_default()
+            .entry(key)
+            .or_insert(url.lastmod);
+        *lastmod = (*lastmod).max(url.lastmod);
+    }
+    let listed_count: usize = listed.values().map(HashMap::len).sum();
+
+    let crawl_on = |host: &str, url: &str| {
+        let mut crawl = DomainCrawl::default();
+        let by_domain = crawled.get(url).map(|s| &s.by_domain).into_iter().flatten();
+        for (domain, domain_crawl) in by_domain {
+            if host.is_empty() || normalize_host(domain) == host {
+                crawl.merge(domain_crawl);
+            }
+        }
+        crawl
+    };
+    let row = |host: &str, url: &str, lastmod: Option<DateTime<FixedOffset>>| {
+        let crawl = crawl_on(host, url);
+        let last_crawled = crawl.seen.last_seen;
+        SitemapUrlRow {
+            host: host.to_string(),
+            url: url.to_string(),
+            lastmod,
+            bot_hits: crawl.hits,
+            last_crawled,
+            days_stale: match (lastmod, last_crawled) {
+                (Some(modified), Some(crawl)) if modified > crawl => {
+                    Some((modified - crawl).num_days())
+                }
+                _ => None,
+            },
+        }
+    };
+
+    let mut crawled_in_sitemap = Vec::new();
+    let mut not_crawled_in_sitemap = Vec::new();
+    for (host, urls) in &listed {
+        for (url, lastmod) in urls {
+            let row = row(host, url, *lastmod);
+            if row.bot_hits > 0 {
+                crawled_in_sitemap.push(row);
+            } else {
+                not_crawled_in_sitemap.push(row);
+            }
+        }
+    }
+
+    // Only pages on the sitemap's hosts; root-relative sitemap URLs cover every host
+    let relative = listed.get("");
+    let mut crawled_outside_sitemap = Vec::new();
+    for (url, bot_url) in crawled {
+        if resource_type::from_url(url) != "html"
+            || relative.is_some_and(|urls| urls.contains_key(url))
+        {
+            continue;
+        }
+        let hosts: BTreeSet<String> = bot_url
+            .by_domain
+            .keys()
+            .map(|d| normalize_host(d))
+            .collect();
+        for host in hosts {
+            let outside = match listed.get(&host) {
+                Some(urls) => !urls.contains_key(url),
+                None => relative.is_some(),
+            };
+            if outside {
+                crawled_outside_sitemap.push(row(&host, url, None));
+            }
+        }
+    }
+    let mut stale: Vec<SitemapUrlRow> = crawled_in_sitemap
+        .iter()
+        .filter(|row| row.days_stale.is_some())
+        .cloned()
+        .collect();
+
+    let coverage_pct = if listed_count == 0 {
+        0.0
+    } else {
+        crawled_in_sitemap.len() as f64 / listed_count as f64 * 100.0
+    };
+    let (crawled_in_sitemap_count, not_crawled_in_sitemap_count) =
+        (crawled_in_sitemap.len(), not_crawled_in_sitemap.len());
+    let (crawled_outside_sitemap_count, stale_count) = (crawled_outside_sitemap.len(), stale.len());
+
+    let by_hits = |a: &SitemapUrlRow, b: &SitemapUrlRow| {
+        b.bot_hits
+            .cmp(&a.bot_hits)
+            .then_with(|| a.url.cmp(&b.url))
+            .then_with(|| a.host.cmp(&b.host))
+    };
+    crawled_in_sitemap.sort_by(by_hits);
+    crawled_outside_sitemap.sort_by(by_hits);
+    // Most recently modified first: the pages most worth getting crawled
+    not_crawled_in_sitemap.sort_by(|a, b| {
+        b.lastmod
+            .cmp(&a.lastmod)
+            .then_with(|| a.url.cmp(&b.url))
+            .then_with(|| a.host.cmp(&b.host))
+    });
+    stale.sort_by(|a, b| {
+        b.days_stale
+            .cmp(&a.days_stale)
+            .then_with(|| a.url.cmp(&b.url))
+            .then_with(|| a.host.cmp(&b.host))
+    });
+    for list in [
+        &mut crawled_in_sitemap,
+        &mut not_crawled_in_sitemap,
+        &mut crawled_outside_sitemap,
+        &mut stale,
+    ] {
+        list.truncate(top_n);
+    }
+
+    SitemapCoverage {
+        bot: bot.to_string(),
+        sitemap_urls: sitemap.urls.len(),
+        other_domain_urls,
+        missing_sitemaps: sitemap.missing_sitemaps.clone(),
+        crawled_in_sitemap_count,
+        not_crawled_in_sitemap_count,
+        crawled_outside_sitemap_count,
+        coverage_pct,
+        stale_count,
+        crawled_in_sitemap,
+        not_crawled_in_sitemap,
+        crawled_outside_sitemap,
+        stale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use crate::test_util::TestLine;
+    #[cfg(feature = "cli")]
+    use flate2::write::GzEncoder;
+    #[cfg(feature = "cli")]
+    use flate2::Compression;
+    #[cfg(feature = "cli")]
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://www.example.com/</loc><lastmod>2024-01-01</lastmod></url>
+  <url>
+    <loc><![CDATA[https://example.com/products?id=1&amp;color=red]]></loc>
+    <lastmod>2024-01-20T08:00:00+00:00</lastmod>
+  </url>
+  <url><loc>https://example.com/about</loc></url>
+  <url><loc>https://cdn.other.org/landing</loc></url>
+</urlset>"#;
+
+    #[test]
+    fn test_parse_sitemap_and_index() {
+        let SitemapDocument::UrlSet(urls) = parse_sitemap(URLSET).unwrap() else {
+            panic!("expected a urlset");
+        };
+        assert_eq!(urls.len(), 4);
+        assert_eq!(urls[1].loc, "https://example.com/products?id=1&color=red");
+        assert_eq!(
+            urls[0].lastmod.unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(urls[2].lastmod, None);
+
+        let index = r#"<sitemapindex><sitemap><loc>https://example.com/s/posts.xml.gz</loc></sitemap></sitemapindex>"#;
+        assert_eq!(
+            parse_sitemap(index).unwrap(),
+            SitemapDocument::Index(vec!["https://example.com/s/posts.xml.gz".to_string()])
+        );
+        assert!(parse_sitemap("<html></html>").is_err());
+        assert_eq!(
+            parse_lastmod("2024-03-05T10:30Z").unwrap().to_rfc3339(),
+            "2024-03-05T10:30:00+00:00"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_load_gzipped_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        fs::write(dir.path().join("pages.xml.gz"), encoder.finish().unwrap()).unwrap();
+        fs::write(
+            dir.path().join("sitemap_index.xml"),
+            "<sitemapindex>\
+             <sitemap><loc>https://example.com/pages.xml.gz</loc></sitemap>\
+             <sitemap><loc>https://example.com/gone.xml</loc></sitemap>\
+             </sitemapindex>",
+        )
+        .unwrap();
+
+        let sitemap = Sitemap::load(&dir.path().join("sitemap_index.xml")).unwrap();
+        assert_eq!(sitemap.urls.len(), 4);
+        assert_eq!(
+            sitemap.missing_sitemaps,
+            vec!["https://example.com/gone.xml"]
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let pipeline = Pipeline {
+            normalizer: Some(UrlNormalizer::default()),
+            ..Default::default()
+        };
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let mut stats = pipeline.new_statistics();
+        for (url, day) in [
+            ("/", "05"),
+            ("/products?id=1&color=red", "10"),
+            ("/blog/new", "12"),
+            ("/app.js", "12"),
+        ] {
+            let line = TestLine::new("example.com", url, 200, googlebot)
+                .time(&format!("{}/Jan/2024:12:00:00 +0000", day))
+                .to_string();
+            stats.add_entry(&pipeline.process_line(&line).unwrap());
+        }
+        let SitemapDocument::UrlSet(urls) = parse_sitemap(URLSET).unwrap() else {
+            unreachable!()
+        };
+        let sitemap = Sitemap {
+            urls,
+            missing_sitemaps: Vec::new(),
+        };
+
+        let report = coverage(
+            &sitemap,
+            &stats,
+            "Googlebot",
+            pipeline.normalizer.as_ref(),
+            10,
+        );
+        assert_eq!(report.sitemap_urls, 4);
+        assert_eq!(report.other_domain_urls, 1);
+        assert_eq!(report.crawled_in_sitemap_count, 2);
+        assert_eq!(report.not_crawled_in_sitemap[0].url, "/about");
+        assert!((report.coverage_pct - 200.0 / 3.0).abs() < 1e-9);
+        // Assets are not expected in a sitemap
+        assert_eq!(report.crawled_outside_sitemap_count, 1);
+        assert_eq!(report.crawled_outside_sitemap[0].url, "/blog/new");
+
+        assert_eq!(report.stale_count, 1);
+        assert_eq!(report.stale[0].url, "/products?color=red&id=1");
+        assert_eq!(report.stale[0].days_stale, Some(9));
+    }
+
+    #[test]
+    fn test_coverage_joins_on_host() {
+        let pipeline = Pipeline::new();
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let mut stats = pipeline.new_statistics();
+        for (domain, url) in [
+            ("www.example.com", "/"),
+            ("blog.example.com", "/about"),
+            ("blog.example.com", "/"),
+        ] {
+            let line = TestLine::new(domain, url, 200, googlebot).to_string();
+            stats.add_entry(&pipeline.process_line(&line).unwrap());
+        }
+        let sitemap = Sitemap {
+            urls: ["https://example.com/", "https://example.com/about"]
+                .iter()
+                .map(|loc| SitemapUrl {
+                    loc: loc.to_string(),
+                    lastmod: None,
+                })
+                .collect(),
+            missing_sitemaps: Vec::new(),
+        };
+
+        let report = coverage(&sitemap, &stats, "Googlebot", None, 10);
+        assert_eq!(report.crawled_in_sitemap_count, 1);
+        assert_eq!(report.crawled_in_sitemap[0].bot_hits, 1);
+        // The blog's /about does not cover the main site's page
+        assert_eq!(report.not_crawled_in_sitemap[0].host, "example.com");
+        assert_eq!(report.not_crawled_in_sitemap[0].url, "/about");
+        // Hosts without sitemap URLs are not reported as crawled outside the sitemap
+        assert_eq!(report.crawled_outside_sitemap_count, 0);
+    }
+}
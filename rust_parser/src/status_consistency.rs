@@ -0,0 +1,278 @@
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::aggregator::{BotUrlStats, StatusClassCounts, UrlStats};
+
+/// Status runs kept per URL; older runs are dropped first
+const MAX_STATUS_RUNS: usize = 32;
+
+/// A stretch of time in which a URL kept returning the same status code
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusRun {
+    pub status: u16,
+    pub hits: u64,
+    pub first_seen: DateTime<FixedOffset>,
+    pub last_seen: DateTime<FixedOffset>,
+}
+
+/// Status codes of a URL as consecutive runs in time order, so a URL alternating
+/// 200 / 404 / 200 shows three runs. Keeps the latest `MAX_STATUS_RUNS` runs.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct StatusTimeline {
+    pub runs: Vec<StatusRun>,
+}
+
+impl StatusTimeline {
+    pub fn add(&mut self, status: u16, dt: DateTime<FixedOffset>) {
+        self.insert(StatusRun {
+            status,
+            hits: 1,
+            first_seen: dt,
+            last_seen: dt,
+        });
+    }
+
+    pub fn merge(&mut self, other: &StatusTimeline) {
+        for run in &other.runs {
+            self.insert(run.clone());
+        }
+    }
+
+    /// Insert a run by start time, joining it with neighbours of the same status
+    fn insert(&mut self, run: StatusRun) {
+        let mut pos = self.runs.partition_point(|r| r.first_seen <= run.first_seen);
+        self.runs.insert(pos, run);
+        if pos > 0 && self.runs[pos - 1].status == self.runs[pos].status {
+            let run = self.runs.remove(pos);
+            pos -= 1;
+            join(&mut self.runs[pos], &run);
+        }
+        if pos + 1 < self.runs.len() && self.runs[pos + 1].status == self.runs[pos].status {
+            let next = self.runs.remove(pos + 1);
+            join(&mut self.runs[pos], &next);
+        }
+        if self.runs.len() > MAX_STATUS_RUNS {
+            self.runs.remove(0);
+        }
+    }
+}
+
+fn join(run: &mut StatusRun, other: &StatusRun) {
+    run.hits += other.hits;
+    run.first_seen = run.first_seen.min(other.first_seen);
+    run.last_seen = run.last_seen.max(other.last_seen);
+}
+
+/// How bad a mix of status classes is for a single URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// e.g. 200 / 304: usually caching, rarely a problem
+    Low,
+    /// Redirects mixed with errors, or 4xx mixed with 5xx
+    Medium,
+    /// Content that is sometimes missing (200 / 404)
+    High,
+    /// Content that sometimes fails (200 / 5xx)
+    Critical,
+}
+
+/// A URL (optionally for one bot) that returned more than one status class
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusInconsistency {
+    pub url: String,
+    pub bot: Option<String>,
+    pub severity: Severity,
+    pub hits: u64,
+    /// Share of hits outside the most common status class, as a percentage
+    pub minority_share: f64,
+    #[serde(flatten)]
+    pub status_classes: StatusClassCounts,
+    pub status_codes: HashMap<u16, u64>,
+    /// When each status code was returned, as runs in time order
+    pub status_runs: Vec<StatusRun>,
+}
+
+/// Worst severity among the status classes present, if more than one is present
+fn classify(classes: &StatusClassCounts) -> Option<Severity> {
+    let ok = classes.status_2xx > 0;
+    let redirect = classes.status_3xx > 0;
+    let client_error = classes.status_4xx > 0;
+    let server_error = classes.status_5xx > 0;
+
+    if ok && server_error {
+        Some(Severity::Critical)
+    } else if ok && client_error {
+        Some(Severity::High)
+    } else if redirect as u8 + client_error as u8 + server_error as u8 > 1 {
+        Some(Severity::Medium)
+    } else if ok && redirect {
+        Some(Severity::Low)
+    } else {
+        None
+    }
+}
+
+fn build(
+    url: &str,
+    bot: Option<&str>,
+    status_codes: &HashMap<u16, u64>,
+    status_runs: &StatusTimeline,
+) -> Option<StatusInconsistency> {
+    let mut classes = StatusClassCounts::default();
+    for (code, count) in status_codes {
+        classes.add_count(*code, *count);
+    }
+    let severity = classify(&classes)?;
+
+    let hits: u64 = status_codes.values().sum();
+    let dominant = [
+        classes.status_2xx,
+        classes.status_3xx,
+        classes.status_4xx,
+        classes.status_5xx,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or(0);
+
+    Some(StatusInconsistency {
+        url: url.to_string(),
+        bot: bot.map(str::to_string),
+        severity,
+        hits,
+        minority_share: (hits - dominant) as f64 / hits as f64 * 100.0,
+        status_classes: classes,
+        status_codes: status_codes.clone(),
+        status_runs: status_runs.runs.clone(),
+    })
+}
+
+fn rank(items: &mut [StatusInconsistency]) {
+    items.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| b.hits.cmp(&a.hits))
+            .then_with(|| a.url.cmp(&b.url))
+    });
+}
+
+/// URLs with mixed status classes across all traffic, ranked by severity then hits
+pub fn find_inconsistencies(hits_by_url: &HashMap<String, UrlStats>) -> Vec<StatusInconsistency> {
+    let mut items: Vec<StatusInconsistency> = hits_by_url
+        .iter()
+        .filter_map(|(url, stats)| build(url, None, &stats.status_codes, &stats.status_runs))
+        .collect();
+    rank(&mut items);
+    items
+}
+
+/// URLs with mixed status classes as seen by each bot, ranked by severity then hits
+pub fn find_bot_inconsistencies(
+    hits_by_bot_url: &HashMap<String, HashMap<String, BotUrlStats>>,
+) -> Vec<StatusInconsistency> {
+    let mut items: Vec<StatusInconsistency> = hits_by_bot_url
+        .iter()
+        .flat_map(|(bot, urls)| {
+            urls.iter().filter_map(move |(url, stats)| {
+                build(url, Some(bot), &stats.status_codes, &stats.status_runs)
+            })
+        })
+        .collect();
+    rank(&mut items);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregator::Statistics;
+    use crate::test_util::TestLine;
+
+    fn add(stats: &mut Statistics, time: &str, url: &str, status: u16, ua: &str) {
+        let line = TestLine::new("example.com", url, status, ua)
+            .time(&format!("01/Jan/2024:{} +0000", time))
+            .to_string();
+        stats.add_entry(&crate::parse_line(&line).unwrap());
+    }
+
+    #[test]
+    fn test_severity_ranking() {
+        let mut stats = Statistics::new();
+        add(&mut stats, "10:00:00", "/flapping", 200, "Mozilla/5.0");
+        add(&mut stats, "11:00:00", "/flapping", 503, "Mozilla/5.0");
+        add(&mut stats, "12:00:00", "/flapping", 200, "Mozilla/5.0");
+        add(&mut stats, "10:00:00", "/cached", 200, "Mozilla/5.0");
+        add(&mut stats, "10:00:00", "/cached", 304, "Mozilla/5.0");
+        add(&mut stats, "10:00:00", "/missing", 200, "Mozilla/5.0");
+        add(&mut stats, "10:00:00", "/missing", 404, "Mozilla/5.0");
+        add(&mut stats, "10:00:00", "/stable", 200, "Mozilla/5.0");
+
+        let found = find_inconsistencies(&stats.hits_by_url);
+        let urls: Vec<_> = found.iter().map(|i| (i.url.as_str(), i.severity)).collect();
+        assert_eq!(
+            urls,
+            vec![
+                ("/flapping", Severity::Critical),
+                ("/missing", Severity::High),
+                ("/cached", Severity::Low),
+            ]
+        );
+
+        let flapping = &found[0];
+        assert!((flapping.minority_share - 100.0 / 3.0).abs() < 1e-9);
+        let runs: Vec<_> = flapping.status_runs.iter().map(|r| r.status).collect();
+        assert_eq!(runs, vec![200, 503, 200]);
+        assert_eq!(flapping.status_runs[1].first_seen.to_rfc3339(), "2024-01-01T11:00:00+00:00");
+    }
+
+    #[test]
+    fn test_status_timeline_merge() {
+        let at = |time: &str| {
+            DateTime::parse_from_rfc3339(&format!("2024-01-01T{}+00:00", time)).unwrap()
+        };
+        // Two workers each saw part of the day
+        let mut first = StatusTimeline::default();
+        first.add(200, at("10:00:00"));
+        first.add(200, at("10:30:00"));
+        first.add(404, at("11:00:00"));
+        let mut second = StatusTimeline::default();
+        second.add(404, at("11:30:00"));
+        second.add(200, at("12:00:00"));
+        first.merge(&second);
+
+        let runs: Vec<_> = first.runs.iter().map(|r| (r.status, r.hits)).collect();
+        assert_eq!(runs, vec![(200, 2), (404, 2), (200, 1)]);
+        assert_eq!(first.runs[1].last_seen, at("11:30:00"));
+    }
+
+    #[test]
+    fn test_per_bot_inconsistencies() {
+        let mut stats = Statistics::new();
+        add(&mut stats, "10:00:00", "/page", 200, "Googlebot/2.1");
+        add(&mut stats, "10:05:00", "/page", 404, "Googlebot/2.1");
+        add(&mut stats, "10:00:00", "/page", 200, "bingbot/2.0");
+
+        let found = find_bot_inconsistencies(&stats.hits_by_bot_url);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].bot.as_deref(), Some("Googlebot"));
+        assert_eq!(found[0].severity, Severity::High);
+    }
+
+    #[test]
+    fn test_classify_medium() {
+        let classes = StatusClassCounts {
+            status_3xx: 1,
+            status_5xx: 1,
+            ..Default::default()
+        };
+        assert_eq!(classify(&classes), Some(Severity::Medium));
+        let classes = StatusClassCounts {
+            status_4xx: 1,
+            ..Default::default()
+        };
+        assert_eq!(classify(&classes), None);
+    }
+}
@@ -0,0 +1,83 @@
+//! Log lines for unit tests
+
+use std::fmt;
+
+/// A log line in the `[server]:::[domain]:::ip ...` format with defaults for the
+/// fields a test does not care about
+pub(crate) struct TestLine {
+    domain: String,
+    ip: String,
+    time: String,
+    method: String,
+    url: String,
+    status: u16,
+    bytes: u64,
+    user_agent: String,
+    extra: String,
+}
+
+impl TestLine {
+    pub(crate) fn new(domain: &str, url: &str, status: u16, user_agent: &str) -> Self {
+        TestLine {
+            domain: domain.to_string(),
+            ip: "66.249.66.1".to_string(),
+            time: "01/Jan/2024:12:00:00 +0000".to_string(),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            status,
+            bytes: 100,
+            user_agent: user_agent.to_string(),
+            extra: String::new(),
+        }
+    }
+
+    pub(crate) fn ip(mut self, ip: &str) -> Self {
+        self.ip = ip.to_string();
+        self
+    }
+
+    /// Timestamp as logged, e.g. `01/Jan/2024:12:00:00 +0000`
+    pub(crate) fn time(mut self, time: &str) -> Self {
+        self.time = time.to_string();
+        self
+    }
+
+    pub(crate) fn method(mut self, method: &str) -> Self {
+        self.method = method.to_string();
+        self
+    }
+
+    pub(crate) fn bytes(mut self, bytes: u64) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    /// Fields appended after the default ones, like a response time
+    pub(crate) fn extra(mut self, extra: &str) -> Self {
+        self.extra = format!(" {}", extra);
+        self
+    }
+}
+
+impl fmt::Display for TestLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"[s]:::[{}]:::{} - - [{}] "{} {} HTTP/1.1" {} {} "-" "{}" "-"{}"#,
+            self.domain,
+            self.ip,
+            self.time,
+            self.method,
+            self.url,
+            self.status,
+            self.bytes,
+            self.user_agent,
+            self.extra
+        )
+    }
+}
+
+/// A log line with the default IP, timestamp and size
+pub(crate) fn test_line(domain: &str, url: &str, status: u16, user_agent: &str) -> String {
+    TestLine::new(domain, url, status, user_agent).to_string()
+}
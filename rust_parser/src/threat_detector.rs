@@ -0,0 +1,174 @@
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexSet};
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::parser::LogEntry;
+
+/// Built-in rule set, one `category  regex` pair per line. First match wins.
+const DEFAULT_RULES: &str = r#"
+# Path traversal
+path_traversal  (?i)(\.\./|\.\.\\|/etc/passwd|/etc/shadow|/proc/self/environ|win\.ini|boot\.ini)
+
+# SQL injection
+sqli  (?i)(union(\s|\+)+(all(\s|\+)+)?select|'(\s|\+)*or(\s|\+)*'?\d|(\s|\+)or(\s|\+)+1=1|sleep\(\d+\)|benchmark\(|information_schema|waitfor(\s|\+)+delay)
+
+# Cross-site scripting
+xss  (?i)(<script|javascript:|onerror(\s|\+)*=|onload(\s|\+)*=|<svg|<iframe|alert\(|document\.cookie)
+
+# Web shells and upload endpoints
+shell_upload  (?i)(/(shell|cmd|c99|r57|wso|webshell|alfa|b374k)[a-z0-9_-]*\.(php|asp|aspx|jsp)|/uploader?\.php|eval\(|base64_decode\(|[?&](cmd|exec)=)
+
+# Config and secret probing
+config_probe  (?i)(/\.env|/\.git/|/\.svn/|/\.htaccess|/\.htpasswd|/\.aws/|/\.ssh/|/\.ds_store|/wp-config\.php|/web\.config|/phpinfo\.php|/server-status|\.(bak|old|sql|swp)$)
+
+# CMS scanning
+cms_scan  (?i)(/wp-login\.php|/wp-admin|/xmlrpc\.php|/wp-content/plugins/|/wp-includes/|/administrator/|/phpmyadmin|/pma/|/typo3|/joomla)
+"#;
+
+/// Compiled built-in rules, shared by every default pipeline
+static DEFAULT_THREAT_RULES: Lazy<ThreatRules> = Lazy::new(|| {
+    ThreatRules::parse(DEFAULT_RULES).expect("Failed to compile default threat rules")
+});
+
+/// A single detection rule
+#[derive(Debug, Clone)]
+pub struct ThreatRule {
+    pub category: String,
+    pub pattern: Regex,
+}
+
+/// Ordered set of threat detection rules
+#[derive(Debug, Clone)]
+pub struct ThreatRules {
+    pub rules: Vec<ThreatRule>,
+    /// All patterns in rule order, so a URL is scanned once for every rule
+    set: RegexSet,
+}
+
+impl Default for ThreatRules {
+    fn default() -> Self {
+        DEFAULT_THREAT_RULES.clone()
+    }
+}
+
+impl ThreatRules {
+    /// Parse rules from text: `category  regex` per line, `#` starts a comment
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut rules = Vec::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (category, pattern) = line
+                .split_once(char::is_whitespace)
+                .map(|(c, p)| (c, p.trim()))
+                .filter(|(_, p)| !p.is_empty())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: expected `category regex`", line_no + 1),
+                    )
+                })?;
+
+            let pattern = Regex::new(pattern).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_no + 1, e))
+            })?;
+
+            rules.push(ThreatRule {
+                category: category.to_string(),
+                pattern,
+            });
+        }
+
+        let set = RegexSet::new(rules.iter().map(|rule| rule.pattern.as_str()))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(ThreatRules { rules, set })
+    }
+
+    /// Load rules from a file
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Return the category of the first rule matching the URL
+    pub fn detect(&self, url: &str) -> Option<&str> {
+        let first_match = |s: &str| self.set.matches(s).iter().next();
+        let index = match percent_decode(url) {
+            Cow::Borrowed(_) => first_match(url),
+            Cow::Owned(decoded) => match (first_match(url), first_match(&decoded)) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }?;
+        Some(self.rules[index].category.as_str())
+    }
+
+    /// Tag a log entry with its threat category (if any)
+    pub fn enrich(&self, entry: &mut LogEntry) {
+        if let Some(category) = self.detect(&entry.url) {
+            entry.threat_category = category.to_string();
+        }
+    }
+}
+
+/// Decode `%XX` escapes so encoded probes (e.g. `%2e%2e%2f`) match the rules,
+/// borrowing URLs without any `%`
+fn percent_decode(s: &str) -> Cow<'_, str> {
+    if !s.contains('%') {
+        return Cow::Borrowed(s);
+    }
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push((hi << 4) | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    Cow::Owned(String::from_utf8_lossy(&out).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules() {
+        let rules = ThreatRules::default();
+        assert_eq!(rules.detect("/../../etc/passwd"), Some("path_traversal"));
+        assert_eq!(rules.detect("/%2e%2e/%2e%2e/etc/passwd"), Some("path_traversal"));
+        assert_eq!(rules.detect("/item?id=1%20UNION%20SELECT%20password"), Some("sqli"));
+        assert_eq!(rules.detect("/search?q=<script>alert(1)</script>"), Some("xss"));
+        assert_eq!(rules.detect("/.env"), Some("config_probe"));
+        assert_eq!(rules.detect("/.git/config"), Some("config_probe"));
+        assert_eq!(rules.detect("/wp-login.php"), Some("cms_scan"));
+        assert_eq!(rules.detect("/uploads/shell.php"), Some("shell_upload"));
+        assert_eq!(rules.detect("/products/shoes?page=2"), None);
+        // Rule order decides between the raw and the decoded URL
+        assert_eq!(rules.detect("/wp-admin/%2e%2e/"), Some("path_traversal"));
+    }
+
+    #[test]
+    fn test_parse_custom_rules() {
+        let rules = ThreatRules::parse("# comment\n\nadmin_probe  ^/admin\n").unwrap();
+        assert_eq!(rules.rules.len(), 1);
+        assert_eq!(rules.detect("/admin/login"), Some("admin_probe"));
+        assert!(ThreatRules::parse("missing_pattern").is_err());
+        assert!(ThreatRules::parse("bad  (unclosed").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, Timelike,
+};
+#[cfg(feature = "cli")]
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+#[cfg(feature = "cli")]
+use crate::parser::LogEntry;
+
+/// Size of the time buckets used for time series stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeBucket {
+    Minute,
+    FiveMinutes,
+    FifteenMinutes,
+    #[default]
+    Hour,
+    Day,
+    /// ISO 8601 week (Monday start), e.g. `2024-W01`
+    Week,
+    Month,
+}
+
+impl FromStr for TimeBucket {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "minute" | "1m" => Ok(TimeBucket::Minute),
+            "5m" => Ok(TimeBucket::FiveMinutes),
+            "15m" => Ok(TimeBucket::FifteenMinutes),
+            "hour" | "1h" => Ok(TimeBucket::Hour),
+            "day" | "1d" => Ok(TimeBucket::Day),
+            "week" => Ok(TimeBucket::Week),
+            "month" => Ok(TimeBucket::Month),
+            _ => Err(format!(
+                "unknown time bucket {:?} (expected minute, 5m, 15m, hour, day, week or month)",
+                s
+            )),
+        }
+    }
+}
+
+impl TimeBucket {
+    /// Label of the bucket containing `dt`, in `dt`'s own offset
+    pub fn key(&self, dt: &DateTime<FixedOffset>) -> String {
+        self.format(self.floor(dt.naive_local()))
+    }
+
+    /// Start of the bucket containing a local time
+    pub fn floor(&self, local: NaiveDateTime) -> NaiveDateTime {
+        let date = local.date();
+        let (hour, minute) = (local.hour(), local.minute());
+        let start = |date: NaiveDate, hour: u32, minute: u32| {
+            date.and_hms_opt(hour, minute, 0).unwrap_or(local)
+        };
+        match self {
+            TimeBucket::Minute => start(date, hour, minute),
+            TimeBucket::FiveMinutes => start(date, hour, minute - minute % 5),
+            TimeBucket::FifteenMinutes => start(date, hour, minute - minute % 15),
+            TimeBucket::Hour => start(date, hour, 0),
+            TimeBucket::Day => start(date, 0, 0),
+            TimeBucket::Week => start(
+                date - Duration::days(date.weekday().num_days_from_monday() as i64),
+                0,
+                0,
+            ),
+            TimeBucket::Month => start(date.with_day(1).unwrap_or(date), 0, 0),
+        }
+    }
+
+    /// Start of the bucket following the one starting at `start`
+    pub fn next(&self, start: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            TimeBucket::Minute => start + Duration::minutes(1),
+            TimeBucket::FiveMinutes => start + Duration::minutes(5),
+            TimeBucket::FifteenMinutes => start + Duration::minutes(15),
+            TimeBucket::Hour => start + Duration::hours(1),
+            TimeBucket::Day => start + Duration::days(1),
+            TimeBucket::Week => start + Duration::weeks(1),
+            TimeBucket::Month => start
+                .checked_add_months(Months::new(1))
+                .unwrap_or(NaiveDateTime::MAX),
+        }
+    }
+
+    /// Start of the bucket preceding the one starting at `start`
+    pub fn previous(&self, start: NaiveDateTime) -> NaiveDateTime {
+        let back = |duration: Duration| {
+            start.checked_sub_signed(duration).unwrap_or(NaiveDateTime::MIN)
+        };
+        match self {
+            TimeBucket::Minute => back(Duration::minutes(1)),
+            TimeBucket::FiveMinutes => back(Duration::minutes(5)),
+            TimeBucket::FifteenMinutes => back(Duration::minutes(15)),
+            TimeBucket::Hour => back(Duration::hours(1)),
+            TimeBucket::Day => back(Duration::days(1)),
+            TimeBucket::Week => back(Duration::weeks(1)),
+            TimeBucket::Month => start
+                .checked_sub_months(Months::new(1))
+                .unwrap_or(NaiveDateTime::MIN),
+        }
+    }
+
+    /// Label of the bucket starting at `start`
+    pub fn format(&self, start: NaiveDateTime) -> String {
+        let pattern = match self {
+            TimeBucket::Minute | TimeBucket::FiveMinutes | TimeBucket::FifteenMinutes => {
+                "%Y-%m-%d %H:%M"
+            }
+            TimeBucket::Hour => "%Y-%m-%d %H:00",
+            TimeBucket::Day => "%Y-%m-%d",
+            TimeBucket::Week => "%G-W%V",
+            TimeBucket::Month => "%Y-%m",
+        };
+        start.format(pattern).to_string()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeBucket::Minute => "minute",
+            TimeBucket::FiveMinutes => "5m",
+            TimeBucket::FifteenMinutes => "15m",
+            TimeBucket::Hour => "hour",
+            TimeBucket::Day => "day",
+            TimeBucket::Week => "week",
+            TimeBucket::Month => "month",
+        }
+    }
+}
+
+/// Convert the entry's timestamp to `tz` so all time stats share one local time
+#[cfg(feature = "cli")]
+pub fn convert_timezone(entry: &mut LogEntry, tz: Tz) {
+    if let Some(dt) = entry.datetime {
+        entry.datetime = Some(dt.with_timezone(&tz).fixed_offset());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "cli")]
+    use crate::test_util::TestLine;
+
+    #[test]
+    fn test_bucket_keys() {
+        let dt = DateTime::parse_from_rfc3339("2024-12-30T10:47:12+00:00").unwrap();
+        let keys: Vec<String> = ["minute", "5m", "15m", "hour", "day", "week", "month"]
+            .iter()
+            .map(|s| s.parse::<TimeBucket>().unwrap().key(&dt))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                "2024-12-30 10:47",
+                "2024-12-30 10:45",
+                "2024-12-30 10:45",
+                "2024-12-30 10:00",
+                "2024-12-30",
+                "2025-W01",
+                "2024-12",
+            ]
+        );
+        assert!("2h".parse::<TimeBucket>().is_err());
+    }
+
+    #[test]
+    fn test_next_bucket() {
+        let start = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        let week = TimeBucket::Week.floor(start("2024-01-03 12:34"));
+        assert_eq!(week, start("2024-01-01 00:00"));
+        assert_eq!(TimeBucket::Week.next(week), start("2024-01-08 00:00"));
+        assert_eq!(
+            TimeBucket::Month.next(start("2024-01-01 00:00")),
+            start("2024-02-01 00:00")
+        );
+        assert_eq!(
+            TimeBucket::FifteenMinutes.next(start("2024-01-01 23:45")),
+            start("2024-01-02 00:00")
+        );
+        assert_eq!(TimeBucket::Week.previous(week), start("2023-12-25 00:00"));
+        assert_eq!(
+            TimeBucket::Month.previous(start("2024-03-01 00:00")),
+            start("2024-02-01 00:00")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_convert_timezone() {
+        let line = TestLine::new("example.com", "/", 200, "Mozilla/5.0")
+            .time("01/Jan/2024:02:30:00 +0100")
+            .to_string();
+        let mut entry = LogEntry::parse(&line).unwrap();
+        convert_timezone(&mut entry, "America/New_York".parse().unwrap());
+        let dt = entry.datetime.unwrap();
+        assert_eq!(dt.to_rfc3339(), "2023-12-31T20:30:00-05:00");
+        assert_eq!(TimeBucket::Day.key(&dt), "2023-12-31");
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::time_bucket::TimeBucket;
+
+/// Maximum number of buckets in the columnar output; the most recent are kept
+const MAX_POINTS: usize = 10_000;
+
+/// Hits per time bucket for several dimensions (bot, status class, ...)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TimeSeries {
+    /// Dimension -> value -> local bucket start -> hits
+    pub dimensions: HashMap<String, HashMap<String, BTreeMap<NaiveDateTime, u64>>>,
+}
+
+/// Gap-filled series laid out in columns: one shared timestamp axis and one count
+/// array per dimension value
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeSeriesColumns {
+    pub bucket: &'static str,
+    pub timestamps: Vec<String>,
+    /// Dimension -> value -> hits aligned with `timestamps`
+    pub series: BTreeMap<String, BTreeMap<String, Vec<u64>>>,
+}
+
+/// Hits by day of week (rows, Monday first) and hour of day (columns)
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Heatmap(pub [[u64; 24]; 7]);
+
+impl Heatmap {
+    pub fn add(&mut self, local: NaiveDateTime) {
+        let day = local.weekday().num_days_from_monday() as usize;
+        self.0[day][local.hour() as usize] += 1;
+    }
+
+    pub fn merge(&mut self, other: &Heatmap) {
+        for (row, other_row) in self.0.iter_mut().zip(&other.0) {
+            for (cell, count) in row.iter_mut().zip(other_row) {
+                *cell += count;
+            }
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.iter().flatten().sum()
+    }
+}
+
+impl TimeSeries {
+    pub fn add(&mut self, dimension: &str, value: &str, bucket: NaiveDateTime) {
+        *self
+            .dimensions
+            .entry(dimension.to_string())
+            .or_default()
+            .entry(value.to_string())
+            .or_default()
+            .entry(bucket)
+            .or_insert(0) += 1;
+    }
+
+    pub fn merge(&mut self, other: &TimeSeries) {
+        for (dimension, values) in &other.dimensions {
+            let target = self.dimensions.entry(dimension.clone()).or_default();
+            for (value, series) in values {
+                let target = target.entry(value.clone()).or_default();
+                for (bucket, count) in series {
+                    *target.entry(*bucket).or_insert(0) += count;
+                }
+            }
+        }
+    }
+
+    /// Re-bucket into a coarser bucket size (e.g. hourly -> daily)
+    pub fn rollup(&self, bucket: TimeBucket) -> TimeSeries {
+        let mut rolled = TimeSeries::default();
+        for (dimension, values) in &self.dimensions {
+            let target = rolled.dimensions.entry(dimension.clone()).or_default();
+            for (value, series) in values {
+                let target = target.entry(value.clone()).or_default();
+                for (start, count) in series {
+                    *target.entry(bucket.floor(*start)).or_insert(0) += count;
+                }
+            }
+        }
+        rolled
+    }
+
+    /// Columnar layout with every bucket between the first and last hit, missing
+    /// buckets as zeros, keeping the `top_n` values of each dimension by hits
+    pub fn to_columns(&self, bucket: TimeBucket, top_n: usize) -> TimeSeriesColumns {
+        self.to_columns_labelled(bucket, top_n, |start| bucket.format(start))
+    }
+
+    /// Same as `to_columns`, formatting each bucket start with `label`
+    pub fn to_columns_labelled(
+        &self,
+        bucket: TimeBucket,
+        top_n: usize,
+        label: impl Fn(NaiveDateTime) -> String,
+    ) -> TimeSeriesColumns {
+        let all_buckets = self
+            .dimensions
+            .values()
+            .flat_map(|v| v.values())
+            .flat_map(|s| s.keys());
+        let (first, last) = match (all_buckets.clone().min(), all_buckets.max()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => {
+                return TimeSeriesColumns {
+                    bucket: bucket.label(),
+                    timestamps: Vec::new(),
+                    series: BTreeMap::new(),
+                }
+            }
+        };
+
+        // Walk back from the last bucket so a stray far-off timestamp cannot blow up the axis
+        let mut axis = vec![last];
+        while axis.len() < MAX_POINTS {
+            let current = axis[axis.len() - 1];
+            let previous = bucket.previous(current);
+            if previous < first || previous >= current {
+                break;
+            }
+            axis.push(previous);
+        }
+        axis.reverse();
+        let positions: HashMap<NaiveDateTime, usize> = axis
+            .iter()
+            .enumerate()
+            .map(|(i, start)| (*start, i))
+            .collect();
+
+        let series = self
+            .dimensions
+            .iter()
+            .map(|(dimension, values)| {
+                let mut ranked: Vec<(&String, u64)> = values
+                    .iter()
+                    .map(|(value, series)| (value, series.values().sum()))
+                    .collect();
+                ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+                let columns = ranked
+                    .into_iter()
+                    .take(top_n)
+                    .map(|(value, _)| {
+                        let mut counts = vec![0; axis.len()];
+                        for (start, count) in &values[value] {
+                            if let Some(&i) = positions.get(start) {
+                                counts[i] = *count;
+                            }
+                        }
+                        (value.clone(), counts)
+                    })
+                    .collect();
+                (dimension.clone(), columns)
+            })
+            .collect();
+
+        TimeSeriesColumns {
+            bucket: bucket.label(),
+            timestamps: axis.into_iter().map(label).collect(),
+            series,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_gap_filled_columns() {
+        let mut left = TimeSeries::default();
+        let mut right = TimeSeries::default();
+        left.add("bot", "Googlebot", at("2024-01-01 10:00"));
+        left.add("bot", "Googlebot", at("2024-01-01 10:00"));
+        right.add("bot", "Googlebot", at("2024-01-01 13:00"));
+        right.add("bot", "bingbot", at("2024-01-01 11:00"));
+        right.add("status_class", "5xx", at("2024-01-01 12:00"));
+        left.merge(&right);
+
+        let columns = left.to_columns(TimeBucket::Hour, 10);
+        assert_eq!(columns.bucket, "hour");
+        assert_eq!(
+            columns.timestamps,
+            vec![
+                "2024-01-01 10:00",
+                "2024-01-01 11:00",
+                "2024-01-01 12:00",
+                "2024-01-01 13:00"
+            ]
+        );
+        assert_eq!(columns.series["bot"]["Googlebot"], vec![2, 0, 0, 1]);
+        assert_eq!(columns.series["bot"]["bingbot"], vec![0, 1, 0, 0]);
+        assert_eq!(columns.series["status_class"]["5xx"], vec![0, 0, 1, 0]);
+
+        let top_only = left.to_columns(TimeBucket::Hour, 1);
+        assert_eq!(top_only.series["bot"].len(), 1);
+        assert!(top_only.series["bot"].contains_key("Googlebot"));
+    }
+
+    #[test]
+    fn test_axis_is_capped_before_gap_filling() {
+        let mut series = TimeSeries::default();
+        series.add("traffic", "bot", at("2024-01-01 10:00"));
+        series.add("traffic", "bot", at("9999-12-31 23:59"));
+
+        let columns = series.to_columns(TimeBucket::Minute, 10);
+        assert_eq!(columns.timestamps.len(), MAX_POINTS);
+        assert_eq!(columns.timestamps.last().unwrap(), "9999-12-31 23:59");
+        assert_eq!(columns.series["traffic"]["bot"].iter().sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let mut left = Heatmap::default();
+        let mut right = Heatmap::default();
+        // 2024-01-01 was a Monday
+        left.add(at("2024-01-01 00:15"));
+        right.add(at("2024-01-07 23:59"));
+        right.add(at("2024-01-14 23:00"));
+        left.merge(&right);
+
+        assert_eq!(left.0[0][0], 1);
+        assert_eq!(left.0[6][23], 2);
+        assert_eq!(left.total(), 3);
+    }
+
+    #[test]
+    fn test_empty() {
+        let columns = TimeSeries::default().to_columns(TimeBucket::Day, 10);
+        assert!(columns.timestamps.is_empty());
+        assert!(columns.series.is_empty());
+    }
+}
@@ -1,18 +1,17 @@
 use wasm_bindgen::prelude::*;
 use serde_json;
 
-use crate::aggregator::Statistics;
-use crate::bot_detector::enrich_entry;
-use crate::parser::LogEntry;
+use crate::pipeline::Pipeline;
+use crate::segmenter::SegmentRules;
 
 /// Parse log content and return JSON statistics
 #[wasm_bindgen]
 pub fn parse_logs(content: &str, top_n: usize) -> String {
-    let mut stats = Statistics::new();
+    let pipeline = Pipeline::new();
+    let mut stats = pipeline.new_statistics();
 
     for line in content.lines() {
-        if let Some(mut entry) = LogEntry::parse(line) {
-            enrich_entry(&mut entry);
+        if let Some(entry) = pipeline.process_line(line) {
             stats.add_entry(&entry);
         }
     }
@@ -25,11 +24,11 @@ pub fn parse_logs(content: &str, top_n: usize) -> String {
 #[wasm_bindgen]
 pub fn parse_multiple_logs(contents_json: &str, top_n: usize) -> String {
     let contents: Vec<String> = serde_json::from_str(contents_json).unwrap_or_default();
+    let pipeline = Pipeline::new();
 
-    let stats = contents.iter().fold(Statistics::new(), |mut acc, content| {
+    let stats = contents.iter().fold(pipeline.new_statistics(), |mut acc, content| {
         for line in content.lines() {
-            if let Some(mut entry) = LogEntry::parse(line) {
-                enrich_entry(&mut entry);
+            if let Some(entry) = pipeline.process_line(line) {
                 acc.add_entry(&entry);
             }
         }
@@ -40,6 +39,27 @@ pub fn parse_multiple_logs(contents_json: &str, top_n: usize) -> String {
     serde_json::to_string_pretty(&summary).unwrap_or_else(|_| "{}".to_string())
 }
 
+/// Parse log content with URL segment rules (`name  scope  regex` per line) and
+/// return JSON statistics, or `{"error": ...}` if the rules are invalid
+#[wasm_bindgen]
+pub fn parse_logs_with_segments(content: &str, segment_rules: &str, top_n: usize) -> String {
+    let mut pipeline = Pipeline::new();
+    match SegmentRules::parse(segment_rules) {
+        Ok(rules) => pipeline.segments = Some(rules),
+        Err(e) => return serde_json::json!({ "error": e.to_string() }).to_string(),
+    }
+    let mut stats = pipeline.new_statistics();
+
+    for line in content.lines() {
+        if let Some(entry) = pipeline.process_line(line) {
+            stats.add_entry(&entry);
+        }
+    }
+
+    let summary = stats.to_summary(top_n);
+    serde_json::to_string_pretty(&summary).unwrap_or_else(|_| "{}".to_string())
+}
+
 /// Get parser version info
 #[wasm_bindgen]
 pub fn get_version() -> String {